@@ -1,63 +1,225 @@
-use crate::{header::HeaderBuilder, validation::ArchiveValidator};
+use crate::{
+    header::{HeaderFormat, constants::*},
+    validation::{ArchiveStructureValidator, ArchiveValidator},
+};
 use std::{
-    fs::{File, OpenOptions},
-    io::{BufReader, Read, Seek, SeekFrom, Write},
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    os::unix::fs::{FileExt, MetadataExt},
     path::Path,
 };
 
-use super::ArchiverError;
+use super::{
+    ArchiverError,
+    codec::Codec,
+    lister::ArchiveLister,
+    reader::Archive,
+    walker::{InputPath, PathWalker, WalkEntry, WalkFilter},
+    writer::Builder,
+};
 
 pub struct ArchiveAppender {
     validator: ArchiveValidator,
-    buffer_size: usize,
+    format: HeaderFormat,
+    filter: WalkFilter,
+    level: Option<i32>,
 }
 
 impl ArchiveAppender {
     pub fn new(validator: ArchiveValidator) -> Self {
         Self {
             validator,
-            buffer_size: 8192,
+            format: HeaderFormat::default(),
+            filter: WalkFilter::default(),
+            level: None,
         }
     }
 
+    pub fn set_format(&mut self, format: HeaderFormat) -> &mut Self {
+        self.format = format;
+        self
+    }
+
+    pub fn set_filter(&mut self, filter: WalkFilter) -> &mut Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Level used when a compressed archive has to be rewritten. Defaults to
+    /// the level recorded in the archive, if the codec keeps one.
+    pub fn set_level(&mut self, level: Option<i32>) -> &mut Self {
+        self.level = level;
+        self
+    }
+
+    /// Appends files and returns a note for every file left out because it
+    /// cannot be archived.
     pub fn append(
         &self,
         archive_path: &Path,
-        files: Vec<impl AsRef<Path>>,
+        files: Vec<impl Into<InputPath>>,
+    ) -> Result<Vec<String>, ArchiverError> {
+        let filter = self.walk_filter(archive_path)?;
+        let mut entries = Vec::new();
+        for file in files {
+            entries.extend(PathWalker::walk_input(&file.into(), &filter)?);
+        }
+        self.append_entries(archive_path, &entries)
+    }
+
+    /// Appends only the files, or files below directories, that are not in
+    /// the archive yet or whose mtime is later than that of their last
+    /// archived copy. Returns a note for every file left out, whether
+    /// unchanged or impossible to archive.
+    pub fn update(
+        &self,
+        archive_path: &Path,
+        files: Vec<impl Into<InputPath>>,
+    ) -> Result<Vec<String>, ArchiverError> {
+        // Later copies of a member supersede earlier ones on extraction.
+        let archived: HashMap<String, u64> = ArchiveLister::new(self.validator.clone())
+            .list(archive_path)?
+            .into_iter()
+            .map(|entry| (entry.name.trim_end_matches('/').to_string(), entry.mtime))
+            .collect();
+
+        let filter = self.walk_filter(archive_path)?;
+        let mut changed = Vec::new();
+        let mut skipped = Vec::new();
+        for file in files {
+            for entry in PathWalker::walk_input(&file.into(), &filter)? {
+                let mtime = fs::symlink_metadata(&entry.path)?.mtime();
+                match archived.get(&entry.name) {
+                    Some(&archived) if mtime <= archived as i64 => {
+                        skipped.push(format!("{}: not newer than archived copy", entry.name));
+                    }
+                    _ => changed.push(entry),
+                }
+            }
+        }
+        if !changed.is_empty() {
+            skipped.extend(self.append_entries(archive_path, &changed)?);
+        }
+        Ok(skipped)
+    }
+
+    /// Appends the members of each archive in `sources`, which may be
+    /// compressed, leaving out their end-of-archive markers. Every source is
+    /// checked with `ArchiveStructureValidator` before anything is written.
+    pub fn concat(
+        &self,
+        archive_path: &Path,
+        sources: &[impl AsRef<Path>],
+    ) -> Result<(), ArchiverError> {
+        let target = fs::canonicalize(archive_path)?;
+        for source in sources {
+            let source = source.as_ref();
+            if fs::canonicalize(source)? == target {
+                return Err(ArchiverError::UnsupportedFeature(format!(
+                    "concatenating {} onto itself",
+                    source.display()
+                )));
+            }
+            ArchiveStructureValidator::validate(source)?;
+        }
+        self.extend(archive_path, |mut out| {
+            for source in sources {
+                Self::copy_members(source.as_ref(), &mut out)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// The walk filter, also leaving out the archive itself.
+    fn walk_filter(&self, archive_path: &Path) -> Result<WalkFilter, ArchiverError> {
+        let mut filter = self.filter.clone();
+        filter.skip_file(&fs::metadata(archive_path)?);
+        Ok(filter)
+    }
+
+    fn append_entries(
+        &self,
+        archive_path: &Path,
+        entries: &[WalkEntry],
+    ) -> Result<Vec<String>, ArchiverError> {
+        let mut notes = Vec::new();
+        self.extend(archive_path, |out| {
+            let mut builder = Builder::new(out);
+            builder
+                .set_format(self.format)
+                .set_filter(self.filter.clone());
+            for entry in entries {
+                builder.append_walk_entry(entry)?;
+            }
+            notes = builder.take_notes();
+            Ok(())
+        })?;
+        Ok(notes)
+    }
+
+    /// Runs `write_members` at the end of the existing members and then
+    /// writes a new end-of-archive marker.
+    fn extend(
+        &self,
+        archive_path: &Path,
+        write_members: impl FnOnce(&mut dyn Write) -> Result<(), ArchiverError>,
     ) -> Result<(), ArchiverError> {
         self.validator.validate(archive_path)?;
+        let (codec, source) = Codec::open(archive_path)?;
+        if codec != Codec::None {
+            // A compressed stream cannot be extended in place, so the
+            // existing members are copied into a new one.
+            return codec.rewrite(archive_path, self.level, |mut archive| {
+                Self::copy_members(archive_path, &mut archive)?;
+                write_members(archive)?;
+                archive.write_all(&[0u8; BLOCK_SIZE * END_MARKER_BLOCKS])?;
+                Ok(())
+            });
+        }
+        // Trailing zero blocks, however many there are, are not part of the
+        // archive.
+        let end = Archive::new(source).members_end()?;
+
         let mut archive = OpenOptions::new()
             .read(true)
             .write(true)
             .open(archive_path)?;
-        archive.seek(SeekFrom::End(-1024))?;
-        for file in files {
-            self.append_file(&mut archive, file)?;
+        archive.seek(SeekFrom::Start(end))?;
+        match write_members(&mut archive) {
+            Ok(()) => {
+                let len = archive.stream_position()?;
+                Self::terminate(&archive, len)
+            }
+            Err(e) => {
+                // Cut off the partial member so the archive stays valid.
+                Self::terminate(&archive, end)?;
+                Err(e)
+            }
         }
+    }
+
+    /// Writes the end-of-archive marker at `offset` and drops whatever
+    /// followed it, such as record padding.
+    fn terminate(archive: &File, offset: u64) -> Result<(), ArchiverError> {
+        let marker = [0u8; BLOCK_SIZE * END_MARKER_BLOCKS];
+        archive.write_all_at(&marker, offset)?;
+        archive.set_len(offset + marker.len() as u64)?;
         Ok(())
     }
 
-    fn append_file(
-        &self,
-        archive: &mut File,
-        file_path: impl AsRef<Path>,
-    ) -> Result<(), ArchiverError> {
-        let header = HeaderBuilder::build_from_path(&file_path)?;
-        archive.write_all(&header)?;
-        let mut reader = BufReader::new(File::open(&file_path)?);
-        let mut buffer = vec![0u8; self.buffer_size];
-        loop {
-            let bytes_read = reader.read(&mut buffer)?;
-            if bytes_read == 0 {
-                break;
-            }
-            archive.write_all(&buffer[..bytes_read])?;
+    /// Copies the raw blocks of every member of the archive at `path` up to,
+    /// but not including, its end-of-archive marker. Members are located
+    /// with `Archive`, so pax sizes are honoured, and then copied from a
+    /// second pass over the stream.
+    fn copy_members(path: &Path, dest: &mut impl Write) -> Result<(), ArchiverError> {
+        let (_, source) = Codec::open(path)?;
+        let end = Archive::new(source).members_end()?;
+        let (_, source) = Codec::open(path)?;
+        let copied = io::copy(&mut source.take(end), dest)?;
+        if copied != end {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
         }
-
-        let pos = archive.stream_position()?;
-        let padding = ((512 - (pos % 512)) % 512) as usize;
-        archive.write_all(&vec![0u8; padding])?;
-
         Ok(())
     }
 }
@@ -1,62 +1,97 @@
 use std::{
-    fs::File,
-    io::{BufReader, Read, Seek, Write},
+    fs::{self, File},
+    io::{BufWriter, Write},
     path::Path,
 };
 
-use crate::header::HeaderBuilder;
+use crate::header::HeaderFormat;
 
-use super::error::ArchiverError;
+use super::{
+    codec::Codec,
+    error::ArchiverError,
+    walker::{InputPath, WalkFilter},
+    writer::Builder,
+};
 
-pub struct ArchiveBuilder {}
+pub struct ArchiveBuilder {
+    format: HeaderFormat,
+    codec: Codec,
+    level: Option<i32>,
+    filter: WalkFilter,
+}
 
-impl ArchiveBuilder {
-    pub const DEFAULT_BUFFER_SIZE: usize = 8192;
-    pub const DEFAULT_BLOCK_SIZE: usize = 512;
+impl Default for ArchiveBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
+impl ArchiveBuilder {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            format: HeaderFormat::default(),
+            codec: Codec::None,
+            level: None,
+            filter: WalkFilter::default(),
+        }
+    }
+
+    pub fn set_format(&mut self, format: HeaderFormat) -> &mut Self {
+        self.format = format;
+        self
+    }
+
+    pub fn set_filter(&mut self, filter: WalkFilter) -> &mut Self {
+        self.filter = filter;
+        self
     }
 
+    /// Forces a codec; otherwise it is inferred from the archive extension.
+    pub fn set_codec(&mut self, codec: Codec) -> &mut Self {
+        self.codec = codec;
+        self
+    }
+
+    pub fn set_level(&mut self, level: Option<i32>) -> &mut Self {
+        self.level = level;
+        self
+    }
+
+    /// Writes a new archive and returns a note for every file left out
+    /// because it cannot be archived.
     pub fn build(
         &self,
         archive_path: &Path,
-        files: Vec<impl AsRef<Path>>,
-    ) -> Result<(), ArchiverError> {
-        let mut archive = File::create(archive_path)?;
-        for file in files {
-            self.add_file(&mut archive, file)?;
-        }
-        self.write_end_marker(&mut archive)
-    }
+        files: Vec<impl Into<InputPath>>,
+    ) -> Result<Vec<String>, ArchiverError> {
+        let codec = match self.codec {
+            Codec::None => Codec::from_extension(archive_path),
+            codec => codec,
+        };
+        codec.validate_level(self.level)?;
+        let file = File::create(archive_path)?;
+        // The archive may lie inside a directory being archived.
+        let mut filter = self.filter.clone();
+        filter.skip_file(&file.metadata()?);
 
-    fn add_file(
-        &self,
-        archive: &mut File,
-        file_path: impl AsRef<Path>,
-    ) -> Result<(), ArchiverError> {
-        let header = HeaderBuilder::build_from_path(&file_path)?;
-        archive.write_all(&header)?;
-        let file = File::open(&file_path)?;
-        let mut reader = BufReader::new(file);
-        let mut buffer = [0; Self::DEFAULT_BUFFER_SIZE];
-        loop {
-            let bytes_read = reader.read(&mut buffer)?;
-            if bytes_read == 0 {
-                break;
+        let result = (|| {
+            let mut builder = Builder::new(codec.encoder(BufWriter::new(file), self.level)?);
+            builder.set_format(self.format).set_filter(filter);
+            for file in files {
+                let input = file.into();
+                match &input.name {
+                    Some(name) => builder.append_path_with_name(&input.path, name)?,
+                    None => builder.append_path(&input.path)?,
+                }
             }
-            archive.write_all(&buffer[..bytes_read])?;
+            let notes = builder.take_notes();
+            builder.into_inner()?.finish()?.flush()?;
+            Ok(notes)
+        })();
+        // Leave no truncated archive behind.
+        if result.is_err() {
+            let _ = fs::remove_file(archive_path);
         }
-        let pos = archive.stream_position()?;
-        let padding = ((Self::DEFAULT_BLOCK_SIZE - (pos as usize % Self::DEFAULT_BLOCK_SIZE))
-            % Self::DEFAULT_BLOCK_SIZE);
-        archive.write_all(&vec![0; padding])?;
-
-        Ok(())
-    }
-
-    fn write_end_marker(&self, archive: &mut File) -> Result<(), ArchiverError> {
-        archive.write_all(&vec![0u8; Self::DEFAULT_BLOCK_SIZE * 2])?;
-        Ok(())
+        result
     }
 }
@@ -0,0 +1,23 @@
+use std::io::{self, BufRead, Read, Write};
+
+use bzip2::{Compression, bufread::MultiBzDecoder, write::BzEncoder};
+
+use super::Compressor;
+
+pub fn decoder<'a>(reader: impl BufRead + 'a) -> io::Result<Box<dyn Read + 'a>> {
+    Ok(Box::new(MultiBzDecoder::new(reader)))
+}
+
+pub fn encoder<W: Write + 'static>(
+    writer: W,
+    level: Option<i32>,
+) -> io::Result<Box<dyn Compressor<W>>> {
+    let level = level.map_or(Compression::default(), |l| Compression::new(l as u32));
+    Ok(Box::new(BzEncoder::new(writer, level)))
+}
+
+impl<W: Write> Compressor<W> for BzEncoder<W> {
+    fn finish(self: Box<Self>) -> io::Result<W> {
+        BzEncoder::finish(*self)
+    }
+}
@@ -0,0 +1,23 @@
+use std::io::{self, BufRead, Read, Write};
+
+use flate2::{Compression, bufread::MultiGzDecoder, write::GzEncoder};
+
+use super::Compressor;
+
+pub fn decoder<'a>(reader: impl BufRead + 'a) -> io::Result<Box<dyn Read + 'a>> {
+    Ok(Box::new(MultiGzDecoder::new(reader)))
+}
+
+pub fn encoder<W: Write + 'static>(
+    writer: W,
+    level: Option<i32>,
+) -> io::Result<Box<dyn Compressor<W>>> {
+    let level = level.map_or(Compression::default(), |l| Compression::new(l as u32));
+    Ok(Box::new(GzEncoder::new(writer, level)))
+}
+
+impl<W: Write> Compressor<W> for GzEncoder<W> {
+    fn finish(self: Box<Self>) -> io::Result<W> {
+        GzEncoder::finish(*self)
+    }
+}
@@ -0,0 +1,317 @@
+#[cfg(feature = "bzip2")]
+mod bzip2;
+mod gzip;
+#[cfg(feature = "xz")]
+mod xz;
+#[cfg(feature = "zstd")]
+mod zstd;
+
+use crate::header::{HeaderValidator, constants::BLOCK_SIZE};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    process,
+};
+
+/// A compressing writer. `finish` writes the codec trailer and hands back the
+/// underlying writer.
+pub trait Compressor<W: Write>: Write {
+    fn finish(self: Box<Self>) -> io::Result<W>;
+}
+
+/// Compression applied to the whole archive stream. Every variant can be
+/// recognized, but only codecs whose cargo feature is enabled can be used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Codec {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+    Xz,
+    Bzip2,
+}
+
+impl Codec {
+    const COMPRESSED: [Self; 4] = [Self::Gzip, Self::Zstd, Self::Xz, Self::Bzip2];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+            Self::Xz => "xz",
+            Self::Bzip2 => "bzip2",
+        }
+    }
+
+    pub fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            Self::None => &[],
+            Self::Gzip => &["gz", "tgz"],
+            Self::Zstd => &["zst", "tzst"],
+            Self::Xz => &["xz", "txz"],
+            Self::Bzip2 => &["bz2", "tbz2", "tbz"],
+        }
+    }
+
+    /// File extensions of every compressed archive kind rustar recognizes.
+    pub fn all_extensions() -> Vec<&'static str> {
+        Self::COMPRESSED
+            .iter()
+            .flat_map(|codec| codec.extensions())
+            .copied()
+            .collect()
+    }
+
+    fn magic(&self) -> &'static [u8] {
+        match self {
+            Self::None => &[],
+            Self::Gzip => &[0x1f, 0x8b],
+            Self::Zstd => &[0x28, 0xb5, 0x2f, 0xfd],
+            Self::Xz => &[0xfd, b'7', b'z', b'X', b'Z', 0x00],
+            Self::Bzip2 => b"BZh",
+        }
+    }
+
+    fn levels(&self) -> RangeInclusive<i32> {
+        match self {
+            Self::None => 0..=0,
+            Self::Gzip | Self::Xz => 0..=9,
+            Self::Zstd => 1..=22,
+            Self::Bzip2 => 1..=9,
+        }
+    }
+
+    /// Picks a codec from the archive file name, e.g. `.tar.gz` or `.tzst`.
+    pub fn from_extension(path: &Path) -> Self {
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+        Self::COMPRESSED
+            .into_iter()
+            .find(|codec| codec.extensions().contains(&ext.as_str()))
+            .unwrap_or(Self::None)
+    }
+
+    /// Recognizes a codec from the first bytes of a stream. A first block
+    /// that checksums as a tar header means a plain archive, even if the
+    /// member name happens to start like a compressed stream.
+    pub fn sniff(magic: &[u8]) -> Self {
+        if magic.len() >= BLOCK_SIZE && HeaderValidator::validate(&magic[..BLOCK_SIZE]).is_ok() {
+            return Self::None;
+        }
+        Self::COMPRESSED
+            .into_iter()
+            .find(|codec| codec.starts(magic))
+            .unwrap_or(Self::None)
+    }
+
+    fn starts(&self, magic: &[u8]) -> bool {
+        match self {
+            // "BZh" is plain text, so also require the block size digit and
+            // the magic of the first block, or of the end of an empty stream.
+            Self::Bzip2 => {
+                magic.len() >= 10
+                    && magic.starts_with(self.magic())
+                    && (b'1'..=b'9').contains(&magic[3])
+                    && matches!(&magic[4..10], b"1AY&SY" | b"\x17\x72\x45\x38\x50\x90")
+            }
+            codec => magic.starts_with(codec.magic()),
+        }
+    }
+
+    /// Opens an archive for reading, transparently decompressing it based on
+    /// its leading magic bytes.
+    pub fn open(path: &Path) -> io::Result<(Self, Box<dyn Read>)> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let codec = Self::sniff(reader.fill_buf()?);
+        Ok((codec, codec.decoder(reader)?))
+    }
+
+    pub fn decoder<'a>(&self, reader: impl BufRead + 'a) -> io::Result<Box<dyn Read + 'a>> {
+        match self {
+            Self::None => Ok(Box::new(reader)),
+            Self::Gzip => gzip::decoder(reader),
+            #[cfg(feature = "zstd")]
+            Self::Zstd => zstd::decoder(reader),
+            #[cfg(feature = "xz")]
+            Self::Xz => xz::decoder(reader),
+            #[cfg(feature = "bzip2")]
+            Self::Bzip2 => bzip2::decoder(reader),
+            #[allow(unreachable_patterns)]
+            _ => Err(self.unavailable()),
+        }
+    }
+
+    /// Checks that `level` lies within the range the codec supports.
+    pub fn validate_level(&self, level: Option<i32>) -> io::Result<()> {
+        if let Some(level) = level {
+            if *self == Self::None {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "a compression level needs a compression codec",
+                ));
+            }
+            if !self.levels().contains(&level) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "compression level {level} out of range for {} ({}..={})",
+                        self.name(),
+                        self.levels().start(),
+                        self.levels().end()
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Wraps `writer` in a compressor. `level` defaults to the codec's own
+    /// default.
+    pub fn encoder<W: Write + 'static>(
+        &self,
+        writer: W,
+        level: Option<i32>,
+    ) -> io::Result<Encoder<W>> {
+        self.validate_level(level)?;
+        let compressor = match self {
+            Self::None => return Ok(Encoder::Plain(writer)),
+            Self::Gzip => gzip::encoder(writer, level)?,
+            #[cfg(feature = "zstd")]
+            Self::Zstd => zstd::encoder(writer, level)?,
+            #[cfg(feature = "xz")]
+            Self::Xz => xz::encoder(writer, level)?,
+            #[cfg(feature = "bzip2")]
+            Self::Bzip2 => bzip2::encoder(writer, level)?,
+            #[allow(unreachable_patterns)]
+            _ => return Err(self.unavailable()),
+        };
+        Ok(Encoder::Compressed(compressor))
+    }
+
+    /// Replaces the compressed archive at `path` with whatever `write`
+    /// produces, compressed with this codec. The new stream goes to a
+    /// uniquely named file next to the original, which is renamed over it
+    /// only once everything was written and removed otherwise, so a failure
+    /// leaves the original untouched.
+    ///
+    /// Without an explicit `level`, the level recorded in the original
+    /// stream is reused where the format keeps one (gzip and bzip2).
+    pub(crate) fn rewrite<E: From<io::Error>>(
+        &self,
+        path: &Path,
+        level: Option<i32>,
+        write: impl FnOnce(&mut dyn Write) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let level = match level {
+            Some(level) => Some(level),
+            None => self.stored_level(path)?,
+        };
+        let (temp_path, temp) = Self::create_temp(path)?;
+        let result = (|| {
+            temp.set_permissions(fs::metadata(path)?.permissions())?;
+            let mut encoder = self.encoder(BufWriter::new(temp), level)?;
+            write(&mut encoder)?;
+            encoder.finish()?.flush()?;
+            Ok(())
+        })();
+        match result {
+            Ok(()) => Ok(fs::rename(&temp_path, path)?),
+            Err(e) => {
+                let _ = fs::remove_file(&temp_path);
+                Err(e)
+            }
+        }
+    }
+
+    /// Creates a file that did not exist before in the directory of `path`,
+    /// so that it can later be renamed over it.
+    fn create_temp(path: &Path) -> io::Result<(PathBuf, File)> {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        for attempt in 0.. {
+            let temp_path =
+                path.with_file_name(format!(".{}.{}.{}.tmp", name, process::id(), attempt));
+            match OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&temp_path)
+            {
+                Ok(file) => return Ok((temp_path, file)),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        unreachable!()
+    }
+
+    /// Reads the compression level back from the stream header, for the
+    /// formats that record one. gzip only tells the fastest and best levels
+    /// apart from the rest.
+    fn stored_level(&self, path: &Path) -> io::Result<Option<i32>> {
+        let mut header = [0u8; 10];
+        let mut file = File::open(path)?;
+        let len = file.read(&mut header)?;
+        let header = &header[..len];
+        Ok(match self {
+            Self::Gzip => match header.get(8) {
+                Some(2) => Some(9),
+                Some(4) => Some(1),
+                _ => None,
+            },
+            Self::Bzip2 => match header.get(3) {
+                Some(&digit @ b'1'..=b'9') => Some((digit - b'0') as i32),
+                _ => None,
+            },
+            _ => None,
+        })
+    }
+
+    #[allow(dead_code)]
+    fn unavailable(&self) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "{} support is not compiled in (enable the `{}` feature)",
+                self.name(),
+                self.name()
+            ),
+        )
+    }
+}
+
+/// A writer that compresses with the selected codec. `finish` must be called
+/// to write any trailer and get the underlying writer back.
+pub enum Encoder<W: Write> {
+    Plain(W),
+    Compressed(Box<dyn Compressor<W>>),
+}
+
+impl<W: Write> Encoder<W> {
+    pub fn finish(self) -> io::Result<W> {
+        match self {
+            Self::Plain(writer) => Ok(writer),
+            Self::Compressed(compressor) => compressor.finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(writer) => writer.write(buf),
+            Self::Compressed(compressor) => compressor.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Plain(writer) => writer.flush(),
+            Self::Compressed(compressor) => compressor.flush(),
+        }
+    }
+}
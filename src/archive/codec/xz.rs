@@ -0,0 +1,25 @@
+use std::io::{self, BufRead, Read, Write};
+
+use liblzma::{bufread::XzDecoder, write::XzEncoder};
+
+use super::Compressor;
+
+const DEFAULT_LEVEL: u32 = 6;
+
+pub fn decoder<'a>(reader: impl BufRead + 'a) -> io::Result<Box<dyn Read + 'a>> {
+    Ok(Box::new(XzDecoder::new_multi_decoder(reader)))
+}
+
+pub fn encoder<W: Write + 'static>(
+    writer: W,
+    level: Option<i32>,
+) -> io::Result<Box<dyn Compressor<W>>> {
+    let level = level.map_or(DEFAULT_LEVEL, |l| l as u32);
+    Ok(Box::new(XzEncoder::new(writer, level)))
+}
+
+impl<W: Write> Compressor<W> for XzEncoder<W> {
+    fn finish(self: Box<Self>) -> io::Result<W> {
+        XzEncoder::finish(*self)
+    }
+}
@@ -0,0 +1,23 @@
+use std::io::{self, BufRead, Read, Write};
+
+use zstd::stream::{read::Decoder, write::Encoder};
+
+use super::Compressor;
+
+pub fn decoder<'a>(reader: impl BufRead + 'a) -> io::Result<Box<dyn Read + 'a>> {
+    Ok(Box::new(Decoder::with_buffer(reader)?))
+}
+
+pub fn encoder<W: Write + 'static>(
+    writer: W,
+    level: Option<i32>,
+) -> io::Result<Box<dyn Compressor<W>>> {
+    // Level 0 asks zstd for its default level.
+    Ok(Box::new(Encoder::new(writer, level.unwrap_or(0))?))
+}
+
+impl<W: Write> Compressor<W> for Encoder<'static, W> {
+    fn finish(self: Box<Self>) -> io::Result<W> {
+        Encoder::finish(*self)
+    }
+}
@@ -0,0 +1,190 @@
+use crate::{header::EntryType, sys, validation::ArchiveValidator};
+use std::{
+    fs::{self, File},
+    io::{self, Read},
+    os::unix::fs::{FileTypeExt, MetadataExt},
+    path::{Component, Path, PathBuf},
+};
+
+use super::{
+    codec::Codec,
+    error::ArchiverError,
+    reader::{Archive, Entry},
+};
+
+/// Compares archive members with the files they would be extracted to.
+pub struct ArchiveComparer {
+    validator: ArchiveValidator,
+    absolute_names: bool,
+}
+
+impl ArchiveComparer {
+    const CHUNK_SIZE: usize = 64 * 1024;
+
+    pub fn new(validator: ArchiveValidator) -> Self {
+        Self {
+            validator,
+            absolute_names: false,
+        }
+    }
+
+    /// Compares members with absolute names or '..' components against the
+    /// paths they name instead of refusing them, like `extract` does.
+    pub fn set_absolute_names(&mut self, absolute_names: bool) -> &mut Self {
+        self.absolute_names = absolute_names;
+        self
+    }
+
+    /// Checks every member against the file of the same name below
+    /// `directory`, like `tar --compare`. Fails with
+    /// `ArchiverError::Differences` if anything differs.
+    pub fn compare(&self, archive_path: &Path, directory: &Path) -> Result<(), ArchiverError> {
+        self.validator.validate(archive_path)?;
+        let (_, reader) = Codec::open(archive_path)?;
+        self.compare_from(&mut Archive::new(reader), directory)
+    }
+
+    /// Compares the remaining members of an already opened archive stream.
+    pub fn compare_from<R: Read>(
+        &self,
+        reader: &mut Archive<R>,
+        directory: &Path,
+    ) -> Result<(), ArchiverError> {
+        let mut differences = Vec::new();
+        for entry in reader.entries() {
+            let mut entry = entry?;
+            let name = entry.header().name.clone();
+            for difference in self.compare_entry(&mut entry, directory)? {
+                differences.push(format!("{}: {}", name, difference));
+            }
+        }
+        if !differences.is_empty() {
+            return Err(ArchiverError::Differences(differences));
+        }
+        Ok(())
+    }
+
+    /// Lists what differs between one member and the file on disk.
+    fn compare_entry<R: Read>(
+        &self,
+        entry: &mut Entry<'_, R>,
+        directory: &Path,
+    ) -> Result<Vec<&'static str>, ArchiverError> {
+        let header = entry.header().clone();
+        let path = match self.disk_path(directory, &header.name) {
+            Ok(path) => path,
+            Err(refused) => return Ok(vec![refused]),
+        };
+        let metadata = match fs::symlink_metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec!["missing on disk"]),
+            Err(e) => return Err(e.into()),
+        };
+
+        if header.entry_type == EntryType::HardLink {
+            let target = match self.disk_path(directory, &header.linkname) {
+                Ok(target) => target,
+                Err(refused) => return Ok(vec![refused]),
+            };
+            return Ok(match fs::symlink_metadata(target) {
+                Ok(target) if (target.dev(), target.ino()) == (metadata.dev(), metadata.ino()) => {
+                    vec![]
+                }
+                _ => vec!["not linked to its archived target"],
+            });
+        }
+
+        let file_type = metadata.file_type();
+        let same_type = match header.entry_type {
+            EntryType::Directory => file_type.is_dir(),
+            EntryType::Symlink => file_type.is_symlink(),
+            EntryType::CharDevice => file_type.is_char_device(),
+            EntryType::BlockDevice => file_type.is_block_device(),
+            EntryType::Fifo => file_type.is_fifo(),
+            _ => file_type.is_file(),
+        };
+        if !same_type {
+            return Ok(vec!["file type differs"]);
+        }
+
+        let mut differences = Vec::new();
+        // Symlink permissions are meaningless on Linux.
+        if header.entry_type != EntryType::Symlink
+            && header.mode & 0o7777 != (metadata.mode() & 0o7777) as u64
+        {
+            differences.push("mode differs");
+        }
+        if header.uid != metadata.uid() as u64 {
+            differences.push("uid differs");
+        }
+        if header.gid != metadata.gid() as u64 {
+            differences.push("gid differs");
+        }
+        if header.mtime as i64 != metadata.mtime() {
+            differences.push("mtime differs");
+        }
+
+        match header.entry_type {
+            EntryType::Symlink => {
+                if fs::read_link(&path)? != Path::new(&header.linkname) {
+                    differences.push("symlink target differs");
+                }
+            }
+            EntryType::CharDevice | EntryType::BlockDevice => {
+                if sys::split_dev(metadata.rdev()) != (header.devmajor, header.devminor) {
+                    differences.push("device numbers differ");
+                }
+            }
+            EntryType::Directory | EntryType::Fifo => {}
+            _ => {
+                if header.size != metadata.size() {
+                    differences.push("size differs");
+                } else if !Self::same_contents(entry, &path)? {
+                    differences.push("contents differ");
+                }
+            }
+        }
+        Ok(differences)
+    }
+
+    fn same_contents(data: &mut impl Read, path: &Path) -> Result<bool, ArchiverError> {
+        let mut file = File::open(path)?;
+        let mut expected = vec![0u8; Self::CHUNK_SIZE];
+        let mut actual = vec![0u8; Self::CHUNK_SIZE];
+        loop {
+            let n = data.read(&mut expected)?;
+            if n == 0 {
+                // The sizes matched, but the file may have grown since.
+                return Ok(file.read(&mut actual[..1])? == 0);
+            }
+            match file.read_exact(&mut actual[..n]) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(false),
+                Err(e) => return Err(e.into()),
+            }
+            if expected[..n] != actual[..n] {
+                return Ok(false);
+            }
+        }
+    }
+
+    /// Where a member would be extracted to below `directory`, following
+    /// the same rules as `ArchiveExtractor`: a leading '/' is dropped and
+    /// '..' is refused unless absolute names are allowed, in which case both
+    /// are kept.
+    fn disk_path(&self, directory: &Path, name: &str) -> Result<PathBuf, &'static str> {
+        let mut path = directory.to_path_buf();
+        for component in Path::new(name).components() {
+            match component {
+                Component::Normal(part) => path.push(part),
+                Component::CurDir => {}
+                Component::RootDir if self.absolute_names => path = PathBuf::from("/"),
+                Component::RootDir => {}
+                Component::ParentDir if self.absolute_names => path.push(component),
+                Component::ParentDir => return Err("contains '..', not compared"),
+                Component::Prefix(_) => return Err("has a path prefix, not compared"),
+            }
+        }
+        Ok(path)
+    }
+}
@@ -0,0 +1,165 @@
+use crate::{
+    glob::Pattern,
+    header::constants::{BLOCK_SIZE, END_MARKER_BLOCKS},
+    validation::ArchiveValidator,
+};
+use std::{
+    fs::OpenOptions,
+    io::{self, Read, Write},
+    ops::Range,
+    os::unix::fs::FileExt,
+    path::Path,
+};
+
+use super::{ArchiverError, codec::Codec, reader::Archive};
+
+pub struct ArchiveDeleter {
+    validator: ArchiveValidator,
+    level: Option<i32>,
+}
+
+impl ArchiveDeleter {
+    const CHUNK_SIZE: usize = 64 * 1024;
+
+    pub fn new(validator: ArchiveValidator) -> Self {
+        Self {
+            validator,
+            level: None,
+        }
+    }
+
+    /// Level used when a compressed archive is rewritten. Defaults to the
+    /// level recorded in the archive, if the codec keeps one.
+    pub fn set_level(&mut self, level: Option<i32>) -> &mut Self {
+        self.level = level;
+        self
+    }
+
+    /// Removes every member matching one of `members`, or lying below a
+    /// matching directory, and closes the gaps they leave. Uncompressed
+    /// archives are compacted in place; compressed ones are rewritten.
+    ///
+    /// Patterns that match nothing are reported after the other members
+    /// have been deleted.
+    pub fn delete(&self, archive_path: &Path, members: &[Pattern]) -> Result<(), ArchiverError> {
+        self.validator.validate(archive_path)?;
+        let (codec, reader) = Codec::open(archive_path)?;
+        let (kept, found) = Self::kept_ranges(&mut Archive::new(reader), members)?;
+
+        let deleted = found.iter().any(|&found| found);
+        if deleted && codec == Codec::None {
+            Self::compact_in_place(archive_path, &kept)?;
+        } else if deleted {
+            self.rewrite_compressed(archive_path, codec, &kept)?;
+        }
+
+        let missing: Vec<String> = members
+            .iter()
+            .zip(found)
+            .filter(|(_, found)| !found)
+            .map(|(pattern, _)| pattern.as_str().to_string())
+            .collect();
+        if !missing.is_empty() {
+            return Err(ArchiverError::MembersNotFound(missing));
+        }
+        Ok(())
+    }
+
+    /// Returns the byte ranges of the uncompressed stream to keep, ending at
+    /// the last member, and which patterns matched something.
+    fn kept_ranges<R: Read>(
+        archive: &mut Archive<R>,
+        members: &[Pattern],
+    ) -> Result<(Vec<Range<u64>>, Vec<bool>), ArchiverError> {
+        let mut kept = Vec::new();
+        let mut found = vec![false; members.len()];
+        let mut start = 0;
+        let mut end = 0;
+        for entry in archive.entries() {
+            let entry = entry?;
+            let size = entry.header().size;
+            let entry_end =
+                entry.data_offset() + size.div_ceil(BLOCK_SIZE as u64) * BLOCK_SIZE as u64;
+            let mut selected = false;
+            for (pattern, found) in members.iter().zip(found.iter_mut()) {
+                if pattern.matches_prefix(&entry.header().name) {
+                    *found = true;
+                    selected = true;
+                }
+            }
+            if selected {
+                if entry.header_offset() > start {
+                    kept.push(start..entry.header_offset());
+                }
+                start = entry_end;
+            }
+            end = entry_end;
+        }
+        if end > start {
+            kept.push(start..end);
+        }
+        Ok((kept, found))
+    }
+
+    /// Moves the kept ranges towards the start of the file, then writes a
+    /// new end marker and truncates whatever follows it. Data before the
+    /// first deleted member is never touched.
+    fn compact_in_place(archive_path: &Path, kept: &[Range<u64>]) -> Result<(), ArchiverError> {
+        let file = OpenOptions::new()
+            .write(true)
+            .read(true)
+            .open(archive_path)?;
+        let mut buffer = vec![0u8; Self::CHUNK_SIZE];
+        let mut position = 0;
+        for range in kept {
+            if range.start == position {
+                position = range.end;
+                continue;
+            }
+            let mut offset = range.start;
+            while offset < range.end {
+                let len = buffer.len().min((range.end - offset) as usize);
+                file.read_exact_at(&mut buffer[..len], offset)?;
+                file.write_all_at(&buffer[..len], position)?;
+                offset += len as u64;
+                position += len as u64;
+            }
+        }
+        file.write_all_at(&[0u8; BLOCK_SIZE * END_MARKER_BLOCKS], position)?;
+        file.set_len(position + (BLOCK_SIZE * END_MARKER_BLOCKS) as u64)?;
+        Ok(())
+    }
+
+    /// Streams the kept ranges into a new compressed file which then
+    /// replaces the original.
+    fn rewrite_compressed(
+        &self,
+        archive_path: &Path,
+        codec: Codec,
+        kept: &[Range<u64>],
+    ) -> Result<(), ArchiverError> {
+        let (_, mut source) = Codec::open(archive_path)?;
+        codec.rewrite(archive_path, self.level, |mut archive| {
+            let mut position = 0;
+            for range in kept {
+                Self::copy_exact(&mut source, &mut io::sink(), range.start - position)?;
+                Self::copy_exact(&mut source, &mut archive, range.end - range.start)?;
+                position = range.end;
+            }
+            archive.write_all(&[0u8; BLOCK_SIZE * END_MARKER_BLOCKS])?;
+            Ok(())
+        })
+    }
+
+    fn copy_exact(
+        source: &mut impl Read,
+        dest: &mut impl Write,
+        len: u64,
+    ) -> Result<(), ArchiverError> {
+        let copied = io::copy(&mut source.take(len), dest)?;
+        if copied != len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{header::HeaderError, validation::ValidationError};
+use crate::{glob::GlobError, header::HeaderError, validation::ValidationError};
 use std::{fmt, io};
 
 #[derive(Debug)]
@@ -7,6 +7,16 @@ pub enum ArchiverError {
     Io(io::Error),
     UnsupportedFeature(String),
     HeaderError(HeaderError),
+    Glob(GlobError),
+    /// Members skipped during extraction, each with the reason.
+    RejectedMembers(Vec<String>),
+    /// Requested member names or patterns that matched nothing.
+    MembersNotFound(Vec<String>),
+    /// Members that differ from the filesystem, each with what differs.
+    Differences(Vec<String>),
+    /// A failure along with notes about what was done before it, such as
+    /// existing files that were backed up.
+    WithNotes(Vec<String>, Box<ArchiverError>),
 }
 
 impl fmt::Display for ArchiverError {
@@ -16,6 +26,30 @@ impl fmt::Display for ArchiverError {
             Self::Io(e) => write!(f, "IO error: {}", e),
             Self::UnsupportedFeature(msg) => write!(f, "Unsupported: {}", msg),
             Self::HeaderError(e) => write!(f, "header error: {}", e),
+            Self::Glob(e) => write!(f, "pattern error: {}", e),
+            Self::RejectedMembers(members) => {
+                write!(f, "refused to extract {} member(s):", members.len())?;
+                for member in members {
+                    write!(f, "\n  {}", member)?;
+                }
+                Ok(())
+            }
+            Self::MembersNotFound(names) => {
+                write!(f, "not found in archive: {}", names.join(", "))
+            }
+            Self::WithNotes(notes, e) => {
+                for note in notes {
+                    writeln!(f, "{}", note)?;
+                }
+                write!(f, "{}", e)
+            }
+            Self::Differences(differences) => {
+                write!(f, "{} difference(s) found:", differences.len())?;
+                for difference in differences {
+                    write!(f, "\n  {}", difference)?;
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -37,4 +71,10 @@ impl From<HeaderError> for ArchiverError {
     }
 }
 
+impl From<GlobError> for ArchiverError {
+    fn from(e: GlobError) -> Self {
+        Self::Glob(e)
+    }
+}
+
 impl std::error::Error for ArchiverError {}
@@ -1,65 +1,560 @@
-use crate::{header::HeaderParser, validation::ArchiveValidator};
+use crate::{
+    glob::Pattern,
+    header::{EntryType, ParsedHeader},
+    sys::{self, Dir, OwnerCache},
+    validation::ArchiveValidator,
+};
 use std::{
-    fs::{self, File},
-    io::{BufReader, Read, Write},
-    path::Path,
+    ffi::{OsStr, OsString},
+    fs::{self, Permissions},
+    io::{self, Read},
+    os::unix::fs::PermissionsExt,
+    path::{Component, Path},
+    time::Duration,
+};
+
+use super::{
+    codec::Codec,
+    error::ArchiverError,
+    reader::{Archive, Entry},
 };
 
-use super::error::ArchiverError;
+/// What to do when a member's destination already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverwritePolicy {
+    /// Leave the existing file and report the member as rejected.
+    #[default]
+    Refuse,
+    /// Replace the existing file. Regular files are rewritten in place, so
+    /// other hard links to them see the new contents.
+    Overwrite,
+    /// Leave the existing file.
+    Skip,
+    /// Replace the existing file only if it is not newer than the member.
+    KeepNewer,
+    /// Remove the existing file before creating the member.
+    UnlinkFirst,
+    /// Rename the existing file to `<name>.~N~` before creating the member.
+    Backup,
+}
 
 pub struct ArchiveExtractor {
     validator: ArchiveValidator,
-    overwrite: bool,
+    overwrite: OverwritePolicy,
+    absolute_names: bool,
+    same_owner: bool,
+    numeric_owner: bool,
+    umask: u32,
+    members: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl Default for ArchiveExtractor {
+    fn default() -> Self {
+        Self::new(ArchiveValidator::default())
+    }
 }
 
 impl ArchiveExtractor {
     pub fn new(validator: ArchiveValidator) -> Self {
         Self {
             validator,
-            overwrite: false,
+            overwrite: OverwritePolicy::default(),
+            absolute_names: false,
+            same_owner: sys::is_root(),
+            numeric_owner: false,
+            umask: sys::umask(),
+            members: Vec::new(),
+            exclude: Vec::new(),
         }
     }
 
-    pub fn _set_overwrite(&mut self, overwrite: bool) -> &mut Self {
+    pub fn set_overwrite_policy(&mut self, overwrite: OverwritePolicy) -> &mut Self {
         self.overwrite = overwrite;
         self
     }
 
-    pub fn extract(&self, archive_path: &Path, output_dir: &Path) -> Result<(), ArchiverError> {
+    /// Keep leading '/' and '..' components in member names instead of
+    /// rejecting them, like `tar --absolute-names`.
+    pub fn set_absolute_names(&mut self, absolute_names: bool) -> &mut Self {
+        self.absolute_names = absolute_names;
+        self
+    }
+
+    /// Restore the archived uid and gid. Defaults to whether the process
+    /// runs as root.
+    pub fn set_same_owner(&mut self, same_owner: bool) -> &mut Self {
+        self.same_owner = same_owner;
+        self
+    }
+
+    /// Restore ownership from the archived uid and gid even when the archive
+    /// records owner names that exist on this system.
+    pub fn set_numeric_owner(&mut self, numeric_owner: bool) -> &mut Self {
+        self.numeric_owner = numeric_owner;
+        self
+    }
+
+    /// Bits cleared from archived permissions. Defaults to the process umask.
+    pub fn set_umask(&mut self, umask: u32) -> &mut Self {
+        self.umask = umask;
+        self
+    }
+
+    /// Extracts only members matching one of `members`, or lying below a
+    /// matching directory. Every pattern must match something.
+    pub fn set_members(&mut self, members: Vec<Pattern>) -> &mut Self {
+        self.members = members;
+        self
+    }
+
+    /// Skips members with a path component sequence matching any pattern.
+    pub fn set_exclude(&mut self, exclude: Vec<Pattern>) -> &mut Self {
+        self.exclude = exclude;
+        self
+    }
+
+    pub fn extract(
+        &self,
+        archive_path: &Path,
+        output_dir: &Path,
+    ) -> Result<Vec<String>, ArchiverError> {
         self.validator.validate(archive_path)?;
-        let archive = File::open(archive_path)?;
-        let mut reader = BufReader::new(archive);
-        let mut buffer = [0u8; 512];
-        loop {
-            reader.read_exact(&mut buffer)?;
-            if buffer.iter().all(|&b| b == 0) {
-                break;
-            }
-            let header = HeaderParser::parse(&buffer)?;
-            let output_path = output_dir.join(&header.name);
-            if output_path.exists() && !self.overwrite {
-                return Err(ArchiverError::UnsupportedFeature(
-                    "File exists and overwrite disabled".into(),
+        let (_, reader) = Codec::open(archive_path)?;
+        self.extract_from(&mut Archive::new(reader), output_dir)
+    }
+
+    /// Extracts the remaining members of an already opened archive stream.
+    ///
+    /// Every destination is opened one directory at a time relative to
+    /// `output_dir` without following symlinks, so a link planted by the
+    /// archive cannot redirect later members outside of it. Directory
+    /// permissions and times are applied last, once their contents exist.
+    ///
+    /// Returns a note for every member whose destination already existed and
+    /// was skipped or backed up under the overwrite policy, and for every
+    /// device node or FIFO that could not be created for lack of privileges.
+    /// If extraction fails at the end, the notes come with the error in
+    /// `ArchiverError::WithNotes`.
+    pub fn extract_from<R: Read>(
+        &self,
+        reader: &mut Archive<R>,
+        output_dir: &Path,
+    ) -> Result<Vec<String>, ArchiverError> {
+        fs::create_dir_all(output_dir)?;
+        let root = Dir::open(output_dir)?;
+        let mut rejected = Vec::new();
+        let mut notes = Vec::new();
+        let mut directories = Vec::new();
+        let mut found = vec![false; self.members.len()];
+        let mut owners = OwnerCache::default();
+        for entry in reader.entries() {
+            let mut entry = entry?;
+            if !self.is_selected(&entry.header().name, &mut found) {
+                continue;
+            }
+            let result =
+                self.extract_entry(&root, &mut entry, &mut directories, &mut notes, &mut owners);
+            Self::collect_rejected(result, &entry.header().name, &mut rejected)?;
+        }
+        for (path, header) in directories.iter().rev() {
+            let result = self.restore_directory(&root, path, header, &mut owners);
+            Self::collect_rejected(result, &header.name, &mut rejected)?;
+        }
+
+        let missing: Vec<String> = self
+            .members
+            .iter()
+            .zip(found)
+            .filter(|(_, found)| !found)
+            .map(|(pattern, _)| pattern.as_str().to_string())
+            .collect();
+        let error = if !rejected.is_empty() {
+            ArchiverError::RejectedMembers(rejected)
+        } else if !missing.is_empty() {
+            ArchiverError::MembersNotFound(missing)
+        } else {
+            return Ok(notes);
+        };
+        // Notes still matter when extraction fails, e.g. to find backups.
+        if notes.is_empty() {
+            Err(error)
+        } else {
+            Err(ArchiverError::WithNotes(notes, Box::new(error)))
+        }
+    }
+
+    /// Checks a member against the member and exclude patterns, marking
+    /// every member pattern that selects it as found.
+    fn is_selected(&self, name: &str, found: &mut [bool]) -> bool {
+        if self.exclude.iter().any(|p| p.matches_anywhere(name)) {
+            return false;
+        }
+        if self.members.is_empty() {
+            return true;
+        }
+        let mut selected = false;
+        for (pattern, found) in self.members.iter().zip(found.iter_mut()) {
+            if pattern.matches_prefix(name) {
+                *found = true;
+                selected = true;
+            }
+        }
+        selected
+    }
+
+    /// Records the reasons a member was rejected and passes other errors on.
+    fn collect_rejected(
+        result: Result<(), ArchiverError>,
+        name: &str,
+        rejected: &mut Vec<String>,
+    ) -> Result<(), ArchiverError> {
+        match result {
+            Err(ArchiverError::RejectedMembers(reasons)) => {
+                rejected.extend(
+                    reasons
+                        .into_iter()
+                        .map(|reason| format!("{}: {}", name, reason)),
+                );
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    fn extract_entry<R: Read>(
+        &self,
+        root: &Dir,
+        entry: &mut Entry<'_, R>,
+        directories: &mut Vec<(MemberPath, ParsedHeader)>,
+        notes: &mut Vec<String>,
+        owners: &mut OwnerCache,
+    ) -> Result<(), ArchiverError> {
+        let header = entry.header().clone();
+        let path = self.member_path(&header.name)?;
+        let Some((name, parents)) = path.parts.split_last() else {
+            // "./" and the like name the output directory itself.
+            if header.entry_type == EntryType::Directory {
+                return Ok(());
+            }
+            return Err(Self::reject("has an empty name"));
+        };
+        let parent = Self::open_dirs(root, &path, parents, true)?;
+        if !self.resolve_existing(&parent, name, &header, notes)? {
+            return Ok(());
+        }
+
+        if header.entry_type == EntryType::Directory {
+            match parent.create_dir(name, 0o777) {
+                Err(e) if e.kind() != io::ErrorKind::AlreadyExists => return Err(e.into()),
+                _ => {}
+            }
+            parent
+                .open_dir(name)
+                .map_err(|e| Self::refusal(e, &parent, name))?;
+            directories.push((path, header));
+            return Ok(());
+        }
+        match header.entry_type {
+            EntryType::Symlink => {
+                parent.symlink(OsStr::new(&header.linkname), name)?;
+                self.restore_owner(&parent, name, &header, owners)?;
+                Self::restore_times(&parent, name, &header)?;
+            }
+            EntryType::CharDevice | EntryType::BlockDevice | EntryType::Fifo => {
+                if !Self::make_node(&parent, name, &header, notes)? {
+                    return Ok(());
+                }
+                self.restore_owner(&parent, name, &header, owners)?;
+                parent.chmod(name, self.mode(&header))?;
+                Self::restore_times(&parent, name, &header)?;
+            }
+            EntryType::HardLink => {
+                let target = self
+                    .member_path(&header.linkname)
+                    .map_err(|_| Self::reject("link target is outside the output directory"))?;
+                let Some((source, source_parents)) = target.parts.split_last() else {
+                    return Err(Self::reject("link target has an empty name"));
+                };
+                // A target that was not extracted, e.g. because it was not
+                // selected, only costs this member.
+                let missing = |e: ArchiverError| match e {
+                    ArchiverError::Io(e) if e.kind() == io::ErrorKind::NotFound => {
+                        Self::reject(format!("link target '{}' does not exist", header.linkname))
+                    }
+                    e => e,
+                };
+                let source_dir =
+                    Self::open_dirs(root, &target, source_parents, false).map_err(missing)?;
+                parent
+                    .hard_link(name, &source_dir, source)
+                    .map_err(|e| missing(e.into()))?;
+            }
+            _ => {
+                let mut file = parent
+                    .create_file(name, 0o666)
+                    .map_err(|e| Self::refusal(e, &parent, name))?;
+                io::copy(entry, &mut file)?;
+                // Changing the owner clears set-id bits, so it comes first.
+                self.restore_owner(&parent, name, &header, owners)?;
+                file.set_permissions(Permissions::from_mode(self.mode(&header)))?;
+                Self::restore_times(&parent, name, &header)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies the overwrite policy to an existing destination. Returns
+    /// `false` if the member should not be extracted.
+    fn resolve_existing(
+        &self,
+        parent: &Dir,
+        name: &OsStr,
+        header: &ParsedHeader,
+        notes: &mut Vec<String>,
+    ) -> Result<bool, ArchiverError> {
+        let Some(stat) = parent.lstat(name)? else {
+            return Ok(true);
+        };
+        // Directories are merged into existing ones.
+        if header.entry_type == EntryType::Directory && sys::is_dir(&stat) {
+            return Ok(true);
+        }
+
+        match self.overwrite {
+            OverwritePolicy::Refuse => Err(Self::reject("already exists")),
+            OverwritePolicy::Skip => {
+                notes.push(format!("{}: kept existing file", header.name));
+                Ok(false)
+            }
+            OverwritePolicy::KeepNewer => {
+                let existing =
+                    Duration::new(stat.st_mtime.max(0) as u64, stat.st_mtime_nsec as u32);
+                if existing > Duration::new(header.mtime, header.mtime_nsec) {
+                    notes.push(format!("{}: kept newer existing file", header.name));
+                    return Ok(false);
+                }
+                Self::remove(parent, name)?;
+                Ok(true)
+            }
+            OverwritePolicy::Overwrite => {
+                let in_place = stat.st_mode & libc::S_IFMT == libc::S_IFREG
+                    && matches!(header.entry_type, EntryType::Regular | EntryType::Other(_));
+                if !in_place {
+                    Self::remove(parent, name)?;
+                }
+                Ok(true)
+            }
+            OverwritePolicy::UnlinkFirst => {
+                Self::remove(parent, name)?;
+                Ok(true)
+            }
+            OverwritePolicy::Backup => {
+                let backup = Self::backup_name(parent, name)?;
+                parent.rename(name, &backup)?;
+                notes.push(format!(
+                    "{}: existing file renamed to {}",
+                    header.name,
+                    backup.to_string_lossy()
+                ));
+                Ok(true)
+            }
+        }
+    }
+
+    /// Creates a device node or FIFO. Returns `false`, with a note, if the
+    /// process lacks the privileges to do so.
+    fn make_node(
+        parent: &Dir,
+        name: &OsStr,
+        header: &ParsedHeader,
+        notes: &mut Vec<String>,
+    ) -> Result<bool, ArchiverError> {
+        let (kind, dev, what) = match header.entry_type {
+            EntryType::CharDevice => (
+                libc::S_IFCHR,
+                sys::make_dev(header.devmajor, header.devminor),
+                "character device",
+            ),
+            EntryType::BlockDevice => (
+                libc::S_IFBLK,
+                sys::make_dev(header.devmajor, header.devminor),
+                "block device",
+            ),
+            _ => (libc::S_IFIFO, 0, "FIFO"),
+        };
+        match parent.mknod(name, kind, 0o600, dev) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+                notes.push(format!(
+                    "{}: skipped {}: permission denied",
+                    header.name, what
                 ));
+                Ok(false)
             }
+            Err(e) => Err(e.into()),
+        }
+    }
 
-            if let Some(parent) = output_path.parent() {
-                fs::create_dir_all(parent)?;
+    /// Removes an existing destination; a directory still holding files is
+    /// a conflict rather than an I/O failure.
+    fn remove(parent: &Dir, name: &OsStr) -> Result<(), ArchiverError> {
+        parent.remove(name).map_err(|e| match e.raw_os_error() {
+            Some(libc::ENOTEMPTY | libc::EEXIST) => {
+                Self::reject("would replace a directory that is not empty")
             }
-            let mut file = File::create(&output_path)?;
-            let mut remaining = header.size;
-            let mut chunk = vec![0u8; 8192.min(remaining as usize)];
+            _ => e.into(),
+        })
+    }
 
-            while remaining > 0 {
-                let to_read = chunk.len().min(remaining as usize);
-                reader.read_exact(&mut chunk[..to_read])?;
-                file.write_all(&chunk[..to_read])?;
-                remaining -= to_read as u64;
+    /// Picks the first unused `<name>.~N~`.
+    fn backup_name(parent: &Dir, name: &OsStr) -> Result<OsString, ArchiverError> {
+        for n in 1.. {
+            let mut backup = name.to_os_string();
+            backup.push(format!(".~{}~", n));
+            if parent.lstat(&backup)?.is_none() {
+                return Ok(backup);
             }
-            let padding = (512 - (header.size % 512)) % 512;
-            reader.seek_relative(padding as i64)?;
         }
+        unreachable!()
+    }
+
+    fn restore_directory(
+        &self,
+        root: &Dir,
+        path: &MemberPath,
+        header: &ParsedHeader,
+        owners: &mut OwnerCache,
+    ) -> Result<(), ArchiverError> {
+        let Some((name, parents)) = path.parts.split_last() else {
+            return Ok(());
+        };
+        let parent = Self::open_dirs(root, path, parents, false)?;
+        self.restore_owner(&parent, name, header, owners)?;
+        parent
+            .open_dir(name)
+            .map_err(|e| Self::refusal(e, &parent, name))?
+            .set_mode(self.mode(header))?;
+        Self::restore_times(&parent, name, header)
+    }
+
+    fn restore_owner(
+        &self,
+        parent: &Dir,
+        name: &OsStr,
+        header: &ParsedHeader,
+        owners: &mut OwnerCache,
+    ) -> Result<(), ArchiverError> {
+        if !self.same_owner {
+            return Ok(());
+        }
+        // Names are mapped through the local databases, as ids may differ
+        // between systems; unknown names fall back to the archived ids.
+        let (mut uid, mut gid) = (header.uid as u32, header.gid as u32);
+        if !self.numeric_owner {
+            uid = owners.user_id(&header.uname).unwrap_or(uid);
+            gid = owners.group_id(&header.gname).unwrap_or(gid);
+        }
+        parent.chown(name, uid, gid)?;
+        Ok(())
+    }
 
+    fn restore_times(
+        parent: &Dir,
+        name: &OsStr,
+        header: &ParsedHeader,
+    ) -> Result<(), ArchiverError> {
+        let mtime = Duration::new(header.mtime, header.mtime_nsec);
+        parent.set_times(name, header.atime, mtime)?;
         Ok(())
     }
+
+    fn mode(&self, header: &ParsedHeader) -> u32 {
+        header.mode as u32 & 0o7777 & !self.umask
+    }
+
+    /// Splits a member name into components. Leading '/' is stripped and
+    /// names containing '..' are refused unless absolute names are allowed.
+    fn member_path(&self, name: &str) -> Result<MemberPath, ArchiverError> {
+        let mut path = MemberPath {
+            absolute: false,
+            parts: Vec::new(),
+        };
+        for component in Path::new(name).components() {
+            match component {
+                Component::Normal(part) => path.parts.push(part.to_os_string()),
+                Component::CurDir => {}
+                Component::RootDir => path.absolute = self.absolute_names,
+                Component::ParentDir if self.absolute_names => {
+                    path.parts.push(component.as_os_str().to_os_string())
+                }
+                Component::ParentDir => return Err(Self::reject("contains '..'")),
+                Component::Prefix(_) => return Err(Self::reject("has a path prefix")),
+            }
+        }
+        Ok(path)
+    }
+
+    /// Opens `parts` one directory at a time from where `path` starts,
+    /// creating missing ones if asked, and refuses to pass through symlinks.
+    fn open_dirs(
+        root: &Dir,
+        path: &MemberPath,
+        parts: &[OsString],
+        create: bool,
+    ) -> Result<Dir, ArchiverError> {
+        let mut dir = if path.absolute {
+            Dir::open(Path::new("/"))?
+        } else {
+            root.try_clone()?
+        };
+        for part in parts {
+            dir = match dir.open_dir(part) {
+                Err(e) if create && e.kind() == io::ErrorKind::NotFound => {
+                    match dir.create_dir(part, 0o777) {
+                        Err(e) if e.kind() != io::ErrorKind::AlreadyExists => {
+                            return Err(e.into());
+                        }
+                        _ => {}
+                    }
+                    dir.open_dir(part)
+                }
+                result => result,
+            }
+            .map_err(|e| Self::refusal(e, &dir, part))?;
+        }
+        Ok(dir)
+    }
+
+    /// Reports an open that hit a symlink or a non-directory as a rejected
+    /// member rather than an I/O failure.
+    fn refusal(e: io::Error, dir: &Dir, part: &OsStr) -> ArchiverError {
+        if !matches!(
+            e.raw_os_error(),
+            Some(libc::ELOOP | libc::EMLINK | libc::ENOTDIR)
+        ) {
+            return e.into();
+        }
+        let is_symlink = matches!(
+            dir.lstat(part),
+            Ok(Some(stat)) if stat.st_mode & libc::S_IFMT == libc::S_IFLNK
+        );
+        let part = part.to_string_lossy();
+        if is_symlink {
+            Self::reject(format!("refusing to follow symbolic link '{}'", part))
+        } else {
+            Self::reject(format!("'{}' is not a directory", part))
+        }
+    }
+
+    fn reject(reason: impl Into<String>) -> ArchiverError {
+        ArchiverError::RejectedMembers(vec![reason.into()])
+    }
+}
+
+/// A member name resolved to components below the output directory, or
+/// below '/' for absolute names.
+struct MemberPath {
+    absolute: bool,
+    parts: Vec<OsString>,
 }
@@ -1,11 +1,7 @@
-use crate::{header::HeaderParser, validation::ArchiveValidator};
-use std::{
-    fs::File,
-    io::{BufReader, Read, Seek, SeekFrom},
-    path::Path,
-};
+use crate::{header::EntryType, sys, validation::ArchiveValidator};
+use std::{io::Read, path::Path};
 
-use super::error::ArchiverError;
+use super::{codec::Codec, error::ArchiverError, reader::Archive};
 
 #[derive(Debug)]
 pub struct ArchiveEntry {
@@ -14,12 +10,79 @@ pub struct ArchiveEntry {
     pub mode: u64,
     pub uid: u64,
     pub gid: u64,
+    pub uname: String,
+    pub gname: String,
     pub mtime: u64,
+    pub entry_type: EntryType,
+    pub linkname: String,
+    /// Device numbers, zero unless the entry is a device node.
+    pub devmajor: u64,
+    pub devminor: u64,
+    /// Offsets in the uncompressed stream, see `Entry::header_offset`.
+    pub header_offset: u64,
+    pub data_offset: u64,
+}
+
+impl ArchiveEntry {
+    /// Renders the type and permission bits like `ls -l`, e.g. "drwxr-xr-x".
+    pub fn mode_string(&self) -> String {
+        let kind = match self.entry_type {
+            EntryType::Directory => 'd',
+            EntryType::Symlink => 'l',
+            EntryType::HardLink => 'h',
+            EntryType::CharDevice => 'c',
+            EntryType::BlockDevice => 'b',
+            EntryType::Fifo => 'p',
+            _ => '-',
+        };
+        let bit = |mask: u64, c: char| if self.mode & mask != 0 { c } else { '-' };
+        // Set-id and sticky bits replace the matching execute bit.
+        let exec = |mask: u64, special: u64, set: char| match (
+            self.mode & mask != 0,
+            self.mode & special != 0,
+        ) {
+            (true, true) => set,
+            (false, true) => set.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-',
+        };
+
+        [
+            kind,
+            bit(0o400, 'r'),
+            bit(0o200, 'w'),
+            exec(0o100, 0o4000, 's'),
+            bit(0o040, 'r'),
+            bit(0o020, 'w'),
+            exec(0o010, 0o2000, 's'),
+            bit(0o004, 'r'),
+            bit(0o002, 'w'),
+            exec(0o001, 0o1000, 't'),
+        ]
+        .into_iter()
+        .collect()
+    }
+}
+
+/// How `ArchiveLister::render` prints a listing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ListFormat {
+    /// One name per line, or `tar -tv` style lines with `show_metadata`.
+    #[default]
+    Text,
+    /// A single JSON array of objects.
+    Json,
+    /// One JSON object per line.
+    JsonLines,
+    /// CSV with a header row.
+    Csv,
 }
 
 pub struct ArchiveLister {
     validator: ArchiveValidator,
     show_metadata: bool,
+    numeric_owner: bool,
+    format: ListFormat,
 }
 
 impl ArchiveLister {
@@ -27,6 +90,8 @@ impl ArchiveLister {
         Self {
             validator,
             show_metadata: false,
+            numeric_owner: false,
+            format: ListFormat::default(),
         }
     }
 
@@ -35,32 +100,209 @@ impl ArchiveLister {
         self
     }
 
+    /// Shows owners as uid/gid even when names are known.
+    pub fn numeric_owner(&mut self, numeric: bool) -> &mut Self {
+        self.numeric_owner = numeric;
+        self
+    }
+
+    pub fn set_format(&mut self, format: ListFormat) -> &mut Self {
+        self.format = format;
+        self
+    }
+
+    /// Renders a whole listing in the selected format, ending with a newline
+    /// unless it is empty.
+    pub fn render(&self, entries: &[ArchiveEntry]) -> String {
+        let mut out = String::new();
+        match self.format {
+            ListFormat::Text => {
+                for entry in entries {
+                    out.push_str(&self.format_entry(entry));
+                    out.push('\n');
+                }
+            }
+            ListFormat::Json => {
+                let objects: Vec<String> = entries.iter().map(Self::json_object).collect();
+                out.push_str(&format!("[{}]\n", objects.join(",")));
+            }
+            ListFormat::JsonLines => {
+                for entry in entries {
+                    out.push_str(&Self::json_object(entry));
+                    out.push('\n');
+                }
+            }
+            ListFormat::Csv => {
+                out.push_str(&Self::FIELDS.join(","));
+                out.push('\n');
+                for entry in entries {
+                    let row: Vec<String> = Self::fields(entry)
+                        .iter()
+                        .map(|field| match field {
+                            Field::Str(s) => Self::csv_quote(s),
+                            Field::Num(n) => n.to_string(),
+                        })
+                        .collect();
+                    out.push_str(&row.join(","));
+                    out.push('\n');
+                }
+            }
+        }
+        out
+    }
+
+    const FIELDS: [&str; 14] = [
+        "name",
+        "entry_type",
+        "linkname",
+        "size",
+        "mode",
+        "uid",
+        "gid",
+        "uname",
+        "gname",
+        "mtime",
+        "devmajor",
+        "devminor",
+        "header_offset",
+        "data_offset",
+    ];
+
+    /// Values in the order of `FIELDS`. The mode is an octal string, as in
+    /// the header itself.
+    fn fields(entry: &ArchiveEntry) -> [Field; 14] {
+        [
+            Field::Str(entry.name.clone()),
+            Field::Str(entry.entry_type.to_string()),
+            Field::Str(entry.linkname.clone()),
+            Field::Num(entry.size),
+            Field::Str(format!("{:04o}", entry.mode)),
+            Field::Num(entry.uid),
+            Field::Num(entry.gid),
+            Field::Str(entry.uname.clone()),
+            Field::Str(entry.gname.clone()),
+            Field::Num(entry.mtime),
+            Field::Num(entry.devmajor),
+            Field::Num(entry.devminor),
+            Field::Num(entry.header_offset),
+            Field::Num(entry.data_offset),
+        ]
+    }
+
+    fn json_object(entry: &ArchiveEntry) -> String {
+        let members: Vec<String> = Self::FIELDS
+            .iter()
+            .zip(Self::fields(entry))
+            .map(|(key, field)| match field {
+                Field::Str(s) => format!("\"{}\":{}", key, Self::json_string(&s)),
+                Field::Num(n) => format!("\"{}\":{}", key, n),
+            })
+            .collect();
+        format!("{{{}}}", members.join(","))
+    }
+
+    fn json_string(s: &str) -> String {
+        let mut out = String::with_capacity(s.len() + 2);
+        out.push('"');
+        for c in s.chars() {
+            match c {
+                '"' => out.push_str("\\\""),
+                '\\' => out.push_str("\\\\"),
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                '\t' => out.push_str("\\t"),
+                c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+                c => out.push(c),
+            }
+        }
+        out.push('"');
+        out
+    }
+
+    fn csv_quote(s: &str) -> String {
+        if s.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", s.replace('"', "\"\""))
+        } else {
+            s.to_string()
+        }
+    }
+
+    /// Formats an entry as its name or, with `show_metadata`, as a
+    /// `tar -tv` style line.
+    pub fn format_entry(&self, entry: &ArchiveEntry) -> String {
+        if !self.show_metadata {
+            return entry.name.clone();
+        }
+
+        let name_or_id = |name: &str, id: u64| match name {
+            "" => id.to_string(),
+            _ if self.numeric_owner => id.to_string(),
+            name => name.to_string(),
+        };
+        let owner = format!(
+            "{}/{}",
+            name_or_id(&entry.uname, entry.uid),
+            name_or_id(&entry.gname, entry.gid)
+        );
+        // Device nodes show their numbers where other entries show a size.
+        let size = if entry.entry_type.is_device() {
+            format!("{},{}", entry.devmajor, entry.devminor)
+        } else {
+            entry.size.to_string()
+        };
+        // Owner and size share a column at least 19 wide, as in GNU tar.
+        let width = 19usize.saturating_sub(owner.len() + 1).max(size.len());
+        let mut line = format!(
+            "{} {} {:>width$} {} {}",
+            entry.mode_string(),
+            owner,
+            size,
+            sys::format_local(entry.mtime as i64),
+            entry.name,
+        );
+        match entry.entry_type {
+            EntryType::Symlink => line.push_str(&format!(" -> {}", entry.linkname)),
+            EntryType::HardLink => line.push_str(&format!(" link to {}", entry.linkname)),
+            _ => {}
+        }
+        line
+    }
+
     pub fn list(&self, archive_path: &Path) -> Result<Vec<ArchiveEntry>, ArchiverError> {
         self.validator.validate(archive_path)?;
 
-        let file = File::open(archive_path)?;
-        let mut reader = BufReader::new(file);
-        let mut entries = Vec::new();
-        let mut buffer = [0u8; 512];
+        let (_, reader) = Codec::open(archive_path)?;
+        self.list_from(&mut Archive::new(reader))
+    }
 
-        loop {
-            reader.read_exact(&mut buffer)?;
-            if buffer.iter().all(|&b| b == 0) {
-                break;
-            }
+    /// Lists the remaining members of an already opened archive stream.
+    pub fn list_from<R: Read>(
+        &self,
+        reader: &mut Archive<R>,
+    ) -> Result<Vec<ArchiveEntry>, ArchiverError> {
+        let mut entries = Vec::new();
 
-            let header = HeaderParser::parse(&buffer)?;
+        for entry in reader.entries() {
+            let entry = entry?;
+            let (header_offset, data_offset) = (entry.header_offset(), entry.data_offset());
+            let header = entry.header().clone();
             let entry = ArchiveEntry {
                 name: header.name,
                 size: header.size,
                 mode: header.mode,
                 uid: header.uid,
                 gid: header.gid,
+                uname: header.uname,
+                gname: header.gname,
                 mtime: header.mtime,
+                entry_type: header.entry_type,
+                linkname: header.linkname,
+                devmajor: header.devmajor,
+                devminor: header.devminor,
+                header_offset,
+                data_offset,
             };
             entries.push(entry);
-            let padding = (512 - (header.size % 512)) % 512;
-            reader.seek(SeekFrom::Current((header.size + padding) as i64))?;
         }
 
         Ok(entries)
@@ -74,3 +316,9 @@ impl ArchiveLister {
             .collect())
     }
 }
+
+/// A listing value, kept apart so JSON can tell strings from numbers.
+enum Field {
+    Str(String),
+    Num(u64),
+}
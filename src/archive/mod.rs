@@ -1,28 +1,49 @@
 mod appender;
 mod builder;
+mod codec;
+mod comparer;
+mod deleter;
 mod error;
 mod extractor;
 mod lister;
+mod reader;
+mod walker;
+mod writer;
 
 use std::path::Path;
 
 pub use appender::ArchiveAppender;
 pub use builder::ArchiveBuilder;
+pub use codec::{Codec, Encoder};
+pub use comparer::ArchiveComparer;
+pub use deleter::ArchiveDeleter;
 pub use error::ArchiverError;
-pub use extractor::ArchiveExtractor;
-use lister::ArchiveEntry;
-pub use lister::ArchiveLister;
+pub use extractor::{ArchiveExtractor, OverwritePolicy};
+pub use lister::{ArchiveEntry, ArchiveLister, ListFormat};
+pub use reader::{Archive, Entries, Entry};
+pub use walker::{InputPath, WalkFilter};
+pub use writer::Builder;
 
-use crate::validation::ArchiveValidator;
+use crate::{glob::Pattern, header::HeaderFormat, validation::ArchiveValidator};
 
+/// Path-based front end over `Builder` and `Archive` used by the CLI. It
+/// validates archive names and structure and handles compression.
 pub struct Archiver {
     builder: ArchiveBuilder,
     extractor: ArchiveExtractor,
     lister: ArchiveLister,
     appender: ArchiveAppender,
+    deleter: ArchiveDeleter,
+    comparer: ArchiveComparer,
     validator: ArchiveValidator,
 }
 
+impl Default for Archiver {
+    fn default() -> Self {
+        Self::new(ArchiveValidator::default_extensions())
+    }
+}
+
 impl Archiver {
     pub fn new(allowed_extensions: Vec<String>) -> Self {
         let validator = ArchiveValidator::new(allowed_extensions);
@@ -31,22 +52,112 @@ impl Archiver {
             extractor: ArchiveExtractor::new(validator.clone()),
             lister: ArchiveLister::new(validator.clone()),
             appender: ArchiveAppender::new(validator.clone()),
+            deleter: ArchiveDeleter::new(validator.clone()),
+            comparer: ArchiveComparer::new(validator.clone()),
             validator,
         }
     }
 
+    /// Selects the header format used by `create`, `append` and `update`.
+    pub fn set_format(&mut self, format: HeaderFormat) -> &mut Self {
+        self.builder.set_format(format);
+        self.appender.set_format(format);
+        self
+    }
+
+    /// Sets what `create`, `append` and `update` leave out when walking directories.
+    pub fn set_filter(&mut self, filter: WalkFilter) -> &mut Self {
+        self.builder.set_filter(filter.clone());
+        self.appender.set_filter(filter);
+        self
+    }
+
+    /// Compresses archives written by `create` regardless of their extension.
+    pub fn set_codec(&mut self, codec: Codec) -> &mut Self {
+        self.builder.set_codec(codec);
+        self
+    }
+
+    /// Compression level for archives written by `create`, and for
+    /// compressed archives rewritten by `append`, `update`, `concat` and
+    /// `delete`.
+    pub fn set_compression_level(&mut self, level: Option<i32>) -> &mut Self {
+        self.builder.set_level(level);
+        self.appender.set_level(level);
+        self.deleter.set_level(level);
+        self
+    }
+
+    /// Lets `extract` write, and `diff` read, members with absolute names or
+    /// '..' components.
+    pub fn set_absolute_names(&mut self, absolute_names: bool) -> &mut Self {
+        self.extractor.set_absolute_names(absolute_names);
+        self.comparer.set_absolute_names(absolute_names);
+        self
+    }
+
+    /// Chooses how `extract` treats files that already exist.
+    pub fn set_overwrite_policy(&mut self, policy: OverwritePolicy) -> &mut Self {
+        self.extractor.set_overwrite_policy(policy);
+        self
+    }
+
+    /// Limits `extract` to members matching `members`, minus `exclude`.
+    pub fn set_member_filter(&mut self, members: Vec<Pattern>, exclude: Vec<Pattern>) -> &mut Self {
+        self.extractor.set_members(members).set_exclude(exclude);
+        self
+    }
+
+    /// Makes `format_entry` produce `tar -tv` style lines.
+    pub fn set_verbose(&mut self, verbose: bool) -> &mut Self {
+        self.lister.show_metadata(verbose);
+        self
+    }
+
+    pub fn set_list_format(&mut self, format: ListFormat) -> &mut Self {
+        self.lister.set_format(format);
+        self
+    }
+
+    /// Uses numeric ids instead of owner names when listing and when
+    /// restoring ownership on extract.
+    pub fn set_numeric_owner(&mut self, numeric: bool) -> &mut Self {
+        self.lister.numeric_owner(numeric);
+        self.extractor.set_numeric_owner(numeric);
+        self
+    }
+
+    pub fn set_same_owner(&mut self, same_owner: bool) -> &mut Self {
+        self.extractor.set_same_owner(same_owner);
+        self
+    }
+
+    pub fn set_umask(&mut self, umask: u32) -> &mut Self {
+        self.extractor.set_umask(umask);
+        self
+    }
+
     // Builder methods
+    /// Creates the archive and returns a note for every file left out
+    /// because it cannot be archived, such as sockets.
     pub fn create(
         &self,
         archive_path: &Path,
-        files: Vec<impl AsRef<Path>>,
-    ) -> Result<(), ArchiverError> {
+        files: Vec<impl Into<InputPath>>,
+    ) -> Result<Vec<String>, ArchiverError> {
         self.validator.validate_extension(archive_path)?;
         self.builder.build(archive_path, files)
     }
 
     // Extractor methods
-    pub fn extract(&self, archive_path: &Path, output_dir: &Path) -> Result<(), ArchiverError> {
+    /// Extracts the archive and returns a note for every existing file that
+    /// was skipped or backed up, and for every special file that could not be
+    /// created.
+    pub fn extract(
+        &self,
+        archive_path: &Path,
+        output_dir: &Path,
+    ) -> Result<Vec<String>, ArchiverError> {
         self.extractor.extract(archive_path, output_dir)
     }
 
@@ -55,12 +166,55 @@ impl Archiver {
         self.lister.list(archive_path)
     }
 
+    /// Formats an entry returned by `list` according to the listing options.
+    pub fn format_entry(&self, entry: &ArchiveEntry) -> String {
+        self.lister.format_entry(entry)
+    }
+
+    /// Renders everything returned by `list` in the selected list format.
+    pub fn render_listing(&self, entries: &[ArchiveEntry]) -> String {
+        self.lister.render(entries)
+    }
+
     // Appender methods
+    /// Appends to the archive and returns notes like `create`.
     pub fn append(
         &self,
         archive_path: &Path,
-        files: Vec<impl AsRef<Path>>,
-    ) -> Result<(), ArchiverError> {
+        files: Vec<impl Into<InputPath>>,
+    ) -> Result<Vec<String>, ArchiverError> {
         self.appender.append(archive_path, files)
     }
+
+    /// Appends files that changed since they were archived and returns a
+    /// note for every file that did not.
+    pub fn update(
+        &self,
+        archive_path: &Path,
+        files: Vec<impl Into<InputPath>>,
+    ) -> Result<Vec<String>, ArchiverError> {
+        self.appender.update(archive_path, files)
+    }
+
+    /// Appends the members of every archive in `sources` to the archive.
+    pub fn concat(
+        &self,
+        archive_path: &Path,
+        sources: &[impl AsRef<Path>],
+    ) -> Result<(), ArchiverError> {
+        self.appender.concat(archive_path, sources)
+    }
+
+    // Deleter methods
+    /// Removes members matching `members` from the archive.
+    pub fn delete(&self, archive_path: &Path, members: &[Pattern]) -> Result<(), ArchiverError> {
+        self.deleter.delete(archive_path, members)
+    }
+
+    // Comparer methods
+    /// Compares every member with the file it would be extracted to below
+    /// `directory`, failing with `ArchiverError::Differences` on mismatches.
+    pub fn diff(&self, archive_path: &Path, directory: &Path) -> Result<(), ArchiverError> {
+        self.comparer.compare(archive_path, directory)
+    }
 }
@@ -0,0 +1,326 @@
+use std::{
+    cell::{Cell, RefCell},
+    io::{self, Read, Write},
+    path::Path,
+};
+
+use crate::{
+    header::{
+        EntryType, HeaderError, HeaderParser, HeaderValidator, ParsedHeader, PaxRecord,
+        constants::{BLOCK_SIZE, MAX_EXTENSION_SIZE},
+    },
+    validation::ValidationError,
+};
+
+use super::{error::ArchiverError, extractor::ArchiveExtractor};
+
+/// Reads a tar stream from any `Read` implementation, folding extension
+/// headers (pax records and GNU long names) into the entry they describe.
+///
+/// The stream is expected to be uncompressed; wrap it with a `Codec` decoder
+/// first if needed.
+pub struct Archive<R: Read> {
+    inner: RefCell<R>,
+    global: RefCell<Vec<PaxRecord>>,
+    /// Number of bytes consumed from `inner` so far.
+    position: Cell<u64>,
+}
+
+impl<R: Read> Archive<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner: RefCell::new(inner),
+            global: RefCell::new(Vec::new()),
+            position: Cell::new(0),
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner.into_inner()
+    }
+
+    /// Extracts every remaining member below `output_dir` with the default
+    /// extraction settings, returning notes about existing files left alone
+    /// and special files that could not be created.
+    pub fn unpack(&mut self, output_dir: impl AsRef<Path>) -> Result<Vec<String>, ArchiverError> {
+        ArchiveExtractor::default().extract_from(self, output_dir.as_ref())
+    }
+
+    /// Iterates over the remaining members. Each `Entry` reads its own data;
+    /// whatever is left unread is skipped when the iterator advances.
+    pub fn entries(&mut self) -> Entries<'_, R> {
+        Entries {
+            archive: self,
+            next: self.position.get(),
+            done: false,
+        }
+    }
+
+    /// Returns the next member header, or `None` once the end-of-archive
+    /// marker (or the end of the stream) is reached. The caller must consume
+    /// the member data with `read_data` or `skip_data` before calling again.
+    pub fn next_header(&mut self) -> Result<Option<ParsedHeader>, ArchiverError> {
+        Ok(self.read_header()?.map(|(header, _)| header))
+    }
+
+    /// Copies the member data described by `header` into `out`.
+    pub fn read_data(
+        &mut self,
+        header: &ParsedHeader,
+        out: &mut impl Write,
+    ) -> Result<(), ArchiverError> {
+        let copied = io::copy(&mut self.raw().take(header.size), out)?;
+        if copied != header.size {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        self.skip(Self::padding(header.size))
+    }
+
+    pub fn skip_data(&mut self, header: &ParsedHeader) -> Result<(), ArchiverError> {
+        self.skip(Self::data_len(header.size)?)
+    }
+
+    /// Reads through the remaining members and returns the stream offset just
+    /// past the data of the last one, which is where the end-of-archive
+    /// marker starts and new members can be written.
+    pub(crate) fn members_end(&mut self) -> Result<u64, ArchiverError> {
+        let mut end = self.position.get();
+        for entry in self.entries() {
+            let entry = entry?;
+            let size = entry.header().size;
+            end = Self::offset_after(entry.data_offset(), size)?;
+        }
+        Ok(end)
+    }
+
+    /// Reads the next member header along with the stream offset of its
+    /// first block, counting the pax and GNU extension headers that belong to
+    /// it but not preceding global headers.
+    fn read_header(&self) -> Result<Option<(ParsedHeader, u64)>, ArchiverError> {
+        let mut local = Vec::new();
+        let mut long_name = None;
+        let mut long_link = None;
+        let mut start = self.position.get();
+        loop {
+            let mut block = [0u8; BLOCK_SIZE];
+            if !self.read_block(&mut block)? || block.iter().all(|&b| b == 0) {
+                return Ok(None);
+            }
+
+            // Streams never go through `ArchiveStructureValidator`, so every
+            // header is checked here before any of its fields are trusted.
+            HeaderValidator::validate(&block)?;
+            let mut header = HeaderParser::parse(&block)?;
+            match header.entry_type {
+                EntryType::PaxGlobalHeader => {
+                    let data = self.read_to_vec(header.size)?;
+                    self.global
+                        .borrow_mut()
+                        .extend(HeaderParser::parse_pax(&data)?);
+                    if local.is_empty() && long_name.is_none() && long_link.is_none() {
+                        start = self.position.get();
+                    }
+                }
+                EntryType::PaxHeader => {
+                    let data = self.read_to_vec(header.size)?;
+                    local.extend(HeaderParser::parse_pax(&data)?);
+                }
+                EntryType::GnuLongName => {
+                    let data = self.read_to_vec(header.size)?;
+                    long_name = Some(HeaderParser::parse_long_name(&data)?);
+                }
+                EntryType::GnuLongLink => {
+                    let data = self.read_to_vec(header.size)?;
+                    long_link = Some(HeaderParser::parse_long_name(&data)?);
+                }
+                _ => {
+                    if let Some(name) = long_name {
+                        header.name = name;
+                    }
+                    if let Some(linkname) = long_link {
+                        header.linkname = linkname;
+                    }
+                    HeaderParser::apply_pax(&mut header, &self.global.borrow())?;
+                    HeaderParser::apply_pax(&mut header, &local)?;
+                    return Ok(Some((header, start)));
+                }
+            }
+        }
+    }
+
+    /// Reads the data of an extension header. The buffer grows with what is
+    /// actually read, and sizes beyond `MAX_EXTENSION_SIZE` are refused.
+    fn read_to_vec(&self, size: u64) -> Result<Vec<u8>, ArchiverError> {
+        if size > MAX_EXTENSION_SIZE {
+            return Err(ValidationError::InvalidStructure(format!(
+                "Extension header data of {} bytes exceeds the {} byte limit",
+                size, MAX_EXTENSION_SIZE
+            ))
+            .into());
+        }
+        let mut data = Vec::new();
+        self.raw().take(size).read_to_end(&mut data)?;
+        if data.len() as u64 != size {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        self.skip(Self::padding(size))?;
+        Ok(data)
+    }
+
+    fn skip(&self, len: u64) -> Result<(), ArchiverError> {
+        let skipped = io::copy(&mut self.raw().take(len), &mut io::sink())?;
+        if skipped != len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        Ok(())
+    }
+
+    /// Reads one block, returning `false` on a clean end of stream.
+    fn read_block(&self, block: &mut [u8; BLOCK_SIZE]) -> Result<bool, ArchiverError> {
+        let mut filled = 0;
+        while filled < BLOCK_SIZE {
+            match self.raw().read(&mut block[filled..]) {
+                Ok(0) if filled == 0 => return Ok(false),
+                Ok(0) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(true)
+    }
+
+    fn raw(&self) -> RawReader<'_, R> {
+        RawReader(self)
+    }
+
+    fn padding(size: u64) -> u64 {
+        (BLOCK_SIZE as u64 - size % BLOCK_SIZE as u64) % BLOCK_SIZE as u64
+    }
+
+    /// Member data length including its padding. Sizes are checked when
+    /// headers are parsed, but `ParsedHeader` fields are public.
+    fn data_len(size: u64) -> Result<u64, ArchiverError> {
+        size.checked_add(Self::padding(size))
+            .ok_or_else(|| HeaderError::NumericOverflow.into())
+    }
+
+    /// Stream offset just past the padded data of a member starting at
+    /// `data_start`.
+    fn offset_after(data_start: u64, size: u64) -> Result<u64, ArchiverError> {
+        data_start
+            .checked_add(Self::data_len(size)?)
+            .ok_or_else(|| HeaderError::NumericOverflow.into())
+    }
+}
+
+/// Reads from the underlying stream while keeping `position` up to date.
+struct RawReader<'a, R: Read>(&'a Archive<R>);
+
+impl<R: Read> Read for RawReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.0.inner.borrow_mut().read(buf)?;
+        self.0.position.set(self.0.position.get() + n as u64);
+        Ok(n)
+    }
+}
+
+/// Iterator over the members of an `Archive`, created by `Archive::entries`.
+pub struct Entries<'a, R: Read> {
+    archive: &'a Archive<R>,
+    /// Stream offset of the next header, past the current entry's data.
+    next: u64,
+    done: bool,
+}
+
+impl<'a, R: Read> Entries<'a, R> {
+    fn next_entry(&mut self) -> Result<Option<Entry<'a, R>>, ArchiverError> {
+        let position = self.archive.position.get();
+        if self.next > position {
+            self.archive.skip(self.next - position)?;
+        }
+        let Some((header, header_start)) = self.archive.read_header()? else {
+            return Ok(None);
+        };
+        let data_start = self.archive.position.get();
+        self.next = Archive::<R>::offset_after(data_start, header.size)?;
+        Ok(Some(Entry {
+            archive: self.archive,
+            remaining: header.size,
+            header_start,
+            data_start,
+            header,
+        }))
+    }
+}
+
+impl<'a, R: Read> Iterator for Entries<'a, R> {
+    type Item = Result<Entry<'a, R>, ArchiverError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.next_entry() {
+            Ok(Some(entry)) => Some(Ok(entry)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// A single archive member. Reading from it yields the member's data.
+pub struct Entry<'a, R: Read> {
+    archive: &'a Archive<R>,
+    header: ParsedHeader,
+    header_start: u64,
+    data_start: u64,
+    remaining: u64,
+}
+
+impl<R: Read> Entry<'_, R> {
+    pub fn header(&self) -> &ParsedHeader {
+        &self.header
+    }
+
+    /// Offset in the uncompressed stream of the member's first header block,
+    /// including any pax or GNU extension headers of its own.
+    pub fn header_offset(&self) -> u64 {
+        self.header_start
+    }
+
+    /// Offset in the uncompressed stream where the member's data begins.
+    pub fn data_offset(&self) -> u64 {
+        self.data_start
+    }
+}
+
+impl<R: Read> Read for Entry<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.remaining == 0 || buf.is_empty() {
+            return Ok(0);
+        }
+        let expected = self.data_start + (self.header.size - self.remaining);
+        if self.archive.position.get() != expected {
+            return Err(io::Error::other(format!(
+                "cannot read {}: the archive has moved past it",
+                self.header.name
+            )));
+        }
+        let len = buf
+            .len()
+            .min(usize::try_from(self.remaining).unwrap_or(usize::MAX));
+        let n = self.archive.raw().read(&mut buf[..len])?;
+        if n == 0 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+        }
+        self.remaining -= n as u64;
+        Ok(n)
+    }
+}
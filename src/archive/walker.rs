@@ -0,0 +1,255 @@
+use std::{
+    fs,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    glob::{IgnoreRules, Pattern},
+    header::HeaderError,
+};
+
+use super::error::ArchiverError;
+
+/// A path given to `create`, `append` or `update`, with the archive name
+/// to root its entries at. Without one, entries are rooted at the final
+/// component of the path.
+#[derive(Debug, Clone)]
+pub struct InputPath {
+    pub path: PathBuf,
+    pub name: Option<String>,
+}
+
+impl InputPath {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            name: None,
+        }
+    }
+
+    pub fn with_name(path: impl Into<PathBuf>, name: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            name: Some(name.into()),
+        }
+    }
+}
+
+impl From<PathBuf> for InputPath {
+    fn from(path: PathBuf) -> Self {
+        Self::new(path)
+    }
+}
+
+impl From<&PathBuf> for InputPath {
+    fn from(path: &PathBuf) -> Self {
+        Self::new(path)
+    }
+}
+
+impl From<&Path> for InputPath {
+    fn from(path: &Path) -> Self {
+        Self::new(path)
+    }
+}
+
+impl From<String> for InputPath {
+    fn from(path: String) -> Self {
+        Self::new(path)
+    }
+}
+
+impl From<&str> for InputPath {
+    fn from(path: &str) -> Self {
+        Self::new(path)
+    }
+}
+
+pub struct WalkEntry {
+    pub path: PathBuf,
+    pub name: String,
+}
+
+/// Decides which paths a recursive walk leaves out.
+#[derive(Debug, Clone, Default)]
+pub struct WalkFilter {
+    exclude: Vec<Pattern>,
+    ignore_files: Vec<String>,
+    /// (device, inode) pairs of files never to archive.
+    skip: Vec<(u64, u64)>,
+}
+
+impl WalkFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Leaves out entries whose archive name has a run of components
+    /// matching `pattern`, along with everything below them.
+    pub fn add_exclude(&mut self, pattern: Pattern) -> &mut Self {
+        self.exclude.push(pattern);
+        self
+    }
+
+    /// Reads exclude patterns from a file, one per line. Blank lines and
+    /// lines starting with '#' are skipped.
+    pub fn add_exclude_from(&mut self, path: impl AsRef<Path>) -> Result<&mut Self, ArchiverError> {
+        let text = fs::read_to_string(path)?;
+        for line in text.lines().map(str::trim_end) {
+            if !line.is_empty() && !line.starts_with('#') {
+                self.exclude.push(Pattern::new(line)?);
+            }
+        }
+        Ok(self)
+    }
+
+    /// Reads `.gitignore`-style rules from every file called `name` found in
+    /// a walked directory; they apply to that directory and below.
+    pub fn add_ignore_file(&mut self, name: impl Into<String>) -> &mut Self {
+        self.ignore_files.push(name.into());
+        self
+    }
+
+    /// Leaves out the file `metadata` describes, such as the archive being
+    /// written, wherever the walk meets it.
+    pub fn skip_file(&mut self, metadata: &fs::Metadata) -> &mut Self {
+        self.skip.push((metadata.dev(), metadata.ino()));
+        self
+    }
+
+    fn skips(&self, metadata: &fs::Metadata) -> bool {
+        self.skip.contains(&(metadata.dev(), metadata.ino()))
+    }
+
+    fn excludes(&self, name: &str) -> bool {
+        self.exclude.iter().any(|p| p.matches_anywhere(name))
+    }
+}
+
+/// Ignore rules read while walking, with the archive name of the directory
+/// they were found in.
+type IgnoreStack = Vec<(String, IgnoreRules)>;
+
+pub struct PathWalker;
+
+impl PathWalker {
+    /// Collects `path` and, for directories, everything below it in a stable
+    /// order. Archive names are rooted at the final component of `path`.
+    /// Paths without one, such as `.`, `..` or `/`, are rooted at their
+    /// contents as `./`, since names with '..' or a leading '/' would be
+    /// refused on extraction.
+    pub fn walk(
+        path: impl AsRef<Path>,
+        filter: &WalkFilter,
+    ) -> Result<Vec<WalkEntry>, ArchiverError> {
+        let path = path.as_ref();
+        let name = match path.file_name() {
+            Some(name) => name
+                .to_str()
+                .ok_or_else(|| HeaderError::InvalidFileName(path.to_string_lossy().into_owned()))?,
+            None => ".",
+        };
+        Self::walk_with_name(path, name, filter)
+    }
+
+    /// Walks an input path under its own name, if it has one.
+    pub fn walk_input(
+        input: &InputPath,
+        filter: &WalkFilter,
+    ) -> Result<Vec<WalkEntry>, ArchiverError> {
+        match &input.name {
+            Some(name) => Self::walk_with_name(&input.path, name, filter),
+            None => Self::walk(&input.path, filter),
+        }
+    }
+
+    /// Like `walk`, but roots archive names at `name` instead.
+    pub fn walk_with_name(
+        path: impl AsRef<Path>,
+        name: &str,
+        filter: &WalkFilter,
+    ) -> Result<Vec<WalkEntry>, ArchiverError> {
+        let mut entries = Vec::new();
+        let name = name.trim_end_matches('/').to_string();
+        if !filter.excludes(&name) {
+            Self::walk_into(path.as_ref(), name, filter, &mut Vec::new(), &mut entries)?;
+        }
+        Ok(entries)
+    }
+
+    fn walk_into(
+        path: &Path,
+        name: String,
+        filter: &WalkFilter,
+        ignores: &mut IgnoreStack,
+        entries: &mut Vec<WalkEntry>,
+    ) -> Result<(), ArchiverError> {
+        // Do not follow symlinks: a linked directory is archived as a link.
+        let metadata = fs::symlink_metadata(path)?;
+        if filter.skips(&metadata) {
+            return Ok(());
+        }
+        let is_dir = metadata.is_dir();
+        entries.push(WalkEntry {
+            path: path.to_path_buf(),
+            name: name.clone(),
+        });
+        if !is_dir {
+            return Ok(());
+        }
+
+        let pushed = Self::read_ignore_files(path, &name, filter, ignores)?;
+        let mut children = fs::read_dir(path)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        children.sort();
+        for child in children {
+            let child_name = child.file_name().and_then(|n| n.to_str()).ok_or_else(|| {
+                HeaderError::InvalidFileName(child.to_string_lossy().into_owned())
+            })?;
+            let child_name = format!("{name}/{child_name}");
+            if filter.excludes(&child_name) || Self::ignored(&child, &child_name, ignores)? {
+                continue;
+            }
+            Self::walk_into(&child, child_name, filter, ignores, entries)?;
+        }
+        ignores.truncate(ignores.len() - pushed);
+        Ok(())
+    }
+
+    /// Pushes the rules of any ignore files in `dir`, returning how many.
+    fn read_ignore_files(
+        dir: &Path,
+        name: &str,
+        filter: &WalkFilter,
+        ignores: &mut IgnoreStack,
+    ) -> Result<usize, ArchiverError> {
+        let mut pushed = 0;
+        for file in &filter.ignore_files {
+            match fs::read_to_string(dir.join(file)) {
+                Ok(text) => {
+                    ignores.push((name.to_string(), IgnoreRules::parse(&text)));
+                    pushed += 1;
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(pushed)
+    }
+
+    /// Deeper ignore files take precedence, and within a file the last
+    /// matching rule wins.
+    fn ignored(path: &Path, name: &str, ignores: &IgnoreStack) -> Result<bool, ArchiverError> {
+        if ignores.is_empty() {
+            return Ok(false);
+        }
+        let is_dir = fs::symlink_metadata(path)?.is_dir();
+        Ok(ignores
+            .iter()
+            .rev()
+            .find_map(|(dir, rules)| rules.matched(&name[dir.len() + 1..], is_dir))
+            .unwrap_or(false))
+    }
+}
@@ -0,0 +1,189 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufReader, Read, Write},
+    os::unix::fs::{FileTypeExt, MetadataExt},
+    path::Path,
+};
+
+use crate::{
+    header::{
+        EntryType, HeaderBuilder, HeaderError, HeaderFormat, ParsedHeader,
+        constants::{BLOCK_SIZE, END_MARKER_BLOCKS},
+    },
+    sys::OwnerCache,
+};
+
+use super::{
+    error::ArchiverError,
+    walker::{PathWalker, WalkEntry, WalkFilter},
+};
+
+/// Writes a tar stream into any `Write` implementation.
+///
+/// Call `finish` (or `into_inner`) once all members are added so the
+/// end-of-archive marker gets written.
+pub struct Builder<W: Write> {
+    inner: W,
+    buffer_size: usize,
+    format: HeaderFormat,
+    filter: WalkFilter,
+    /// Archive names of files already written, keyed by (device, inode), so
+    /// that further links to the same file become hard link entries.
+    hard_links: HashMap<(u64, u64), String>,
+    /// Files that `append_path` left out, each with the reason.
+    notes: Vec<String>,
+    owners: OwnerCache,
+    finished: bool,
+}
+
+impl<W: Write> Builder<W> {
+    pub const DEFAULT_BUFFER_SIZE: usize = 8192;
+
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            buffer_size: Self::DEFAULT_BUFFER_SIZE,
+            format: HeaderFormat::default(),
+            filter: WalkFilter::default(),
+            hard_links: HashMap::new(),
+            notes: Vec::new(),
+            owners: OwnerCache::default(),
+            finished: false,
+        }
+    }
+
+    pub fn set_format(&mut self, format: HeaderFormat) -> &mut Self {
+        self.format = format;
+        self
+    }
+
+    /// Sets what `append_path` leaves out when walking directories.
+    pub fn set_filter(&mut self, filter: WalkFilter) -> &mut Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Returns, and forgets, a note for every file that `append_path` left
+    /// out because it cannot be archived, such as sockets.
+    pub fn take_notes(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.notes)
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Adds `path` and, for directories, everything below it. Archive names
+    /// are rooted at the final component of `path`.
+    pub fn append_path(&mut self, path: impl AsRef<Path>) -> Result<(), ArchiverError> {
+        for entry in PathWalker::walk(path, &self.filter)? {
+            self.append_walk_entry(&entry)?;
+        }
+        Ok(())
+    }
+
+    /// Like `append_path`, but stores `path` under `name` in the archive.
+    pub fn append_path_with_name(
+        &mut self,
+        path: impl AsRef<Path>,
+        name: &str,
+    ) -> Result<(), ArchiverError> {
+        for entry in PathWalker::walk_with_name(path, name, &self.filter)? {
+            self.append_walk_entry(&entry)?;
+        }
+        Ok(())
+    }
+
+    /// Adds a member described by `header` whose content is read from `data`.
+    /// Exactly `header.size` bytes are consumed.
+    pub fn append_data(
+        &mut self,
+        header: &ParsedHeader,
+        data: impl Read,
+    ) -> Result<(), ArchiverError> {
+        self.inner
+            .write_all(&HeaderBuilder::build(header, self.format)?)?;
+        let size = header.size;
+        let mut reader = data.take(size);
+        let mut buffer = vec![0u8; self.buffer_size];
+        let mut written = 0u64;
+        loop {
+            let bytes_read = reader.read(&mut buffer)?;
+            if bytes_read == 0 {
+                break;
+            }
+            self.inner.write_all(&buffer[..bytes_read])?;
+            written += bytes_read as u64;
+        }
+        if written != size {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!(
+                    "{} ended before the size recorded in its header",
+                    header.name
+                ),
+            )
+            .into());
+        }
+        let padding = (BLOCK_SIZE - (size as usize % BLOCK_SIZE)) % BLOCK_SIZE;
+        self.inner.write_all(&vec![0u8; padding])?;
+        Ok(())
+    }
+
+    /// Writes the end-of-archive marker. Further calls are no-ops.
+    pub fn finish(&mut self) -> Result<(), ArchiverError> {
+        if !self.finished {
+            self.inner
+                .write_all(&[0u8; BLOCK_SIZE * END_MARKER_BLOCKS])?;
+            self.finished = true;
+        }
+        Ok(())
+    }
+
+    /// Finishes the archive and returns the underlying writer.
+    pub fn into_inner(mut self) -> Result<W, ArchiverError> {
+        self.finish()?;
+        Ok(self.inner)
+    }
+
+    /// Adds a single walked path without descending into it.
+    pub(super) fn append_walk_entry(&mut self, entry: &WalkEntry) -> Result<(), ArchiverError> {
+        let metadata = fs::symlink_metadata(&entry.path)?;
+        // Sockets cannot be recreated from an archive, so GNU tar skips them.
+        if metadata.file_type().is_socket() {
+            self.notes.push(format!("{}: socket ignored", entry.name));
+            return Ok(());
+        }
+        let mut header =
+            ParsedHeader::from_metadata_with_owners(&entry.name, &metadata, &mut self.owners)?;
+
+        match header.entry_type {
+            EntryType::Symlink => {
+                let target = fs::read_link(&entry.path)?;
+                header.linkname = target
+                    .to_str()
+                    .ok_or_else(|| HeaderError::InvalidFileName(target.to_string_lossy().into()))?
+                    .to_string();
+            }
+            EntryType::Regular if metadata.nlink() > 1 => {
+                let key = (metadata.dev(), metadata.ino());
+                if let Some(target) = self.hard_links.get(&key) {
+                    header.entry_type = EntryType::HardLink;
+                    header.linkname = target.clone();
+                    header.size = 0;
+                } else {
+                    self.hard_links.insert(key, header.name.clone());
+                }
+            }
+            _ => {}
+        }
+
+        if header.entry_type == EntryType::Regular {
+            let file = BufReader::new(File::open(&entry.path)?);
+            self.append_data(&header, file)
+        } else {
+            self.append_data(&header, io::empty())
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use std::{fmt, io};
+
+#[derive(Debug)]
+pub enum GlobError {
+    EmptyPattern,
+    UnclosedClass(String),
+    TrailingEscape(String),
+    NoMatch(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for GlobError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::EmptyPattern => write!(f, "empty pattern"),
+            Self::UnclosedClass(pattern) => write!(f, "unclosed '[' in pattern: {}", pattern),
+            Self::TrailingEscape(pattern) => write!(f, "pattern ends with '\\': {}", pattern),
+            Self::NoMatch(pattern) => write!(f, "no files match pattern: {}", pattern),
+            Self::Io(e) => write!(f, "IO error: {}", e),
+        }
+    }
+}
+
+impl From<io::Error> for GlobError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl std::error::Error for GlobError {}
@@ -0,0 +1,89 @@
+use std::{fs, path::PathBuf};
+
+use super::{error::GlobError, pattern::Pattern};
+
+/// Expands a shell-style pattern against the filesystem, in sorted order.
+/// Arguments without wildcards are returned with their escapes removed,
+/// whether or not they exist. A matching directory is returned once, not descended into, and
+/// names starting with '.' only match a component that starts with '.' too.
+pub fn expand(pattern: &str) -> Result<Vec<PathBuf>, GlobError> {
+    let compiled = Pattern::new(pattern)?;
+    if let Some(literal) = compiled.literal() {
+        return Ok(vec![PathBuf::from(literal)]);
+    }
+
+    let components: Vec<&str> = compiled.as_str().split('/').collect();
+    let literal = components.iter().take_while(|c| !is_wildcard(c)).count();
+    let prefix = match components[..literal].join("/") {
+        prefix if prefix.is_empty() && pattern.starts_with('/') => "/".to_string(),
+        prefix => prefix,
+    };
+
+    let mut matches = Vec::new();
+    expand_into(&compiled, &components[literal..], &prefix, &mut matches)?;
+    if matches.is_empty() {
+        return Err(GlobError::NoMatch(pattern.to_string()));
+    }
+    matches.sort();
+    Ok(matches.into_iter().map(PathBuf::from).collect())
+}
+
+/// Expands every pattern in `patterns`, keeping their order.
+pub fn expand_all(patterns: &[impl AsRef<str>]) -> Result<Vec<PathBuf>, GlobError> {
+    let mut paths = Vec::new();
+    for pattern in patterns {
+        paths.extend(expand(pattern.as_ref())?);
+    }
+    Ok(paths)
+}
+
+/// Reads `dir` and recurses into subdirectories while `remaining` still has
+/// components to match, or indefinitely below a `**`.
+fn expand_into(
+    pattern: &Pattern,
+    remaining: &[&str],
+    dir: &str,
+    matches: &mut Vec<String>,
+) -> Result<(), GlobError> {
+    let Some((component, rest)) = remaining.split_first() else {
+        return Ok(());
+    };
+    let globstar = *component == "**";
+    let read_dir = match fs::read_dir(if dir.is_empty() { "." } else { dir }) {
+        Ok(read_dir) => read_dir,
+        // Unreadable or vanished directories simply match nothing.
+        Err(_) => return Ok(()),
+    };
+
+    for child in read_dir {
+        let child = child?;
+        let Some(name) = child.file_name().to_str().map(String::from) else {
+            continue;
+        };
+        if name.starts_with('.') && !component.starts_with('.') {
+            continue;
+        }
+        let path = match dir {
+            "" => name,
+            dir if dir.ends_with('/') => format!("{dir}{name}"),
+            dir => format!("{dir}/{name}"),
+        };
+
+        if pattern.matches(&path) {
+            matches.push(path);
+            continue;
+        }
+        // The entry's file type does not follow symlinks, so linked
+        // directories are not walked.
+        if child.file_type()?.is_dir() {
+            // Below a `**` every depth is a candidate, so keep it at the front.
+            let next = if globstar { remaining } else { rest };
+            expand_into(pattern, next, &path, matches)?;
+        }
+    }
+    Ok(())
+}
+
+fn is_wildcard(component: &str) -> bool {
+    component.contains(['*', '?', '[', '\\'])
+}
@@ -0,0 +1,69 @@
+use super::pattern::Pattern;
+
+/// Rules read from a `.gitignore`-style file, relative to the directory
+/// holding it.
+///
+/// Blank lines and lines starting with '#' are skipped, '!' re-includes a
+/// path, a trailing '/' only matches directories, and a pattern without any
+/// other '/' matches the name at any depth. Lines that are not valid patterns
+/// are ignored, as git does.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreRules {
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    pattern: Pattern,
+    negated: bool,
+    dir_only: bool,
+    anchored: bool,
+}
+
+impl IgnoreRules {
+    pub fn parse(text: &str) -> Self {
+        let rules = text
+            .lines()
+            .filter_map(|line| {
+                let line = line.trim_end();
+                if line.is_empty() || line.starts_with('#') {
+                    return None;
+                }
+                let (negated, line) = match line.strip_prefix('!') {
+                    Some(rest) => (true, rest),
+                    None => (false, line),
+                };
+                let dir_only = line.ends_with('/');
+                let line = line.trim_end_matches('/');
+                let anchored = line.contains('/');
+                let pattern = Pattern::new(line.trim_start_matches('/')).ok()?;
+                Some(Rule {
+                    pattern,
+                    negated,
+                    dir_only,
+                    anchored,
+                })
+            })
+            .collect();
+        Self { rules }
+    }
+
+    /// Returns `Some(true)` if the last matching rule ignores `path`,
+    /// `Some(false)` if it re-includes it, and `None` if no rule matches.
+    /// `path` is relative to the directory the rules were read from.
+    pub fn matched(&self, path: &str, is_dir: bool) -> Option<bool> {
+        let file_name = path.rsplit('/').next().unwrap_or(path);
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| {
+                (is_dir || !rule.dir_only)
+                    && if rule.anchored {
+                        rule.pattern.matches(path)
+                    } else {
+                        rule.pattern.matches(file_name)
+                    }
+            })
+            .map(|rule| !rule.negated)
+    }
+}
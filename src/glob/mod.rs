@@ -0,0 +1,9 @@
+mod error;
+mod expand;
+mod ignore;
+mod pattern;
+
+pub use error::GlobError;
+pub use expand::{expand, expand_all};
+pub use ignore::IgnoreRules;
+pub use pattern::Pattern;
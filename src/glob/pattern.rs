@@ -0,0 +1,190 @@
+use super::error::GlobError;
+
+/// A shell-style pattern matched against '/'-separated paths.
+///
+/// `*` and `?` match within a single path component, `[a-z]` and `[!a-z]`
+/// match one character from a set, `**` as a whole component matches any
+/// number of components, and `\` escapes the next character.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    source: String,
+    tokens: Vec<Token>,
+}
+
+#[derive(Debug, Clone)]
+enum Token {
+    Char(char),
+    AnyChar,
+    AnySeq,
+    /// `**/`: zero or more leading components.
+    AnyDirs,
+    /// A trailing `**`: everything that is left.
+    AnyPath,
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl Pattern {
+    pub fn new(pattern: &str) -> Result<Self, GlobError> {
+        let source = Self::normalize(pattern);
+        if source.is_empty() {
+            return Err(GlobError::EmptyPattern);
+        }
+
+        let chars: Vec<char> = source.chars().collect();
+        let mut tokens = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            match chars[i] {
+                '*' => {
+                    let start = i;
+                    while i < chars.len() && chars[i] == '*' {
+                        i += 1;
+                    }
+                    let whole_component = i - start == 2 && (start == 0 || chars[start - 1] == '/');
+                    if whole_component && i == chars.len() {
+                        tokens.push(Token::AnyPath);
+                    } else if whole_component && chars[i] == '/' {
+                        tokens.push(Token::AnyDirs);
+                        i += 1;
+                    } else {
+                        tokens.push(Token::AnySeq);
+                    }
+                    continue;
+                }
+                '?' => tokens.push(Token::AnyChar),
+                '[' => {
+                    let (token, end) = Self::parse_class(&chars, i)
+                        .ok_or_else(|| GlobError::UnclosedClass(source.clone()))?;
+                    tokens.push(token);
+                    i = end;
+                }
+                '\\' => {
+                    i += 1;
+                    let c = chars
+                        .get(i)
+                        .ok_or_else(|| GlobError::TrailingEscape(source.clone()))?;
+                    tokens.push(Token::Char(*c));
+                }
+                c => tokens.push(Token::Char(c)),
+            }
+            i += 1;
+        }
+
+        Ok(Self { source, tokens })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Whether the pattern can match more than one literal path.
+    pub fn has_wildcards(&self) -> bool {
+        self.tokens.iter().any(|t| !matches!(t, Token::Char(_)))
+    }
+
+    /// The single path a pattern without wildcards stands for, with its
+    /// escapes removed.
+    pub fn literal(&self) -> Option<String> {
+        self.tokens
+            .iter()
+            .map(|t| match t {
+                Token::Char(c) => Some(*c),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Matches the whole path.
+    pub fn matches(&self, path: &str) -> bool {
+        let path: Vec<char> = Self::normalize(path).chars().collect();
+        Self::match_tokens(&self.tokens, &path)
+    }
+
+    /// Matches the path or one of its parent directories, so a pattern naming
+    /// a directory selects everything below it.
+    pub fn matches_prefix(&self, path: &str) -> bool {
+        let path: Vec<char> = Self::normalize(path).chars().collect();
+        (0..=path.len())
+            .filter(|&end| end == path.len() || path[end] == '/')
+            .any(|end| Self::match_tokens(&self.tokens, &path[..end]))
+    }
+
+    /// Matches any run of whole components, so `*.o` or `build` exclude
+    /// entries at any depth along with everything below them.
+    pub fn matches_anywhere(&self, path: &str) -> bool {
+        let path: Vec<char> = Self::normalize(path).chars().collect();
+        let mut starts = (0..path.len()).filter(|&i| i == 0 || path[i - 1] == '/');
+        starts.any(|start| {
+            (start..=path.len())
+                .filter(|&end| end == path.len() || path[end] == '/')
+                .any(|end| Self::match_tokens(&self.tokens, &path[start..end]))
+        })
+    }
+
+    /// Drops a leading "./" and trailing '/' so "./dir/" and "dir" compare
+    /// equal.
+    fn normalize(path: &str) -> String {
+        let mut path = path;
+        while let Some(rest) = path.strip_prefix("./") {
+            path = rest;
+        }
+        path.trim_end_matches('/').to_string()
+    }
+
+    /// Parses a class starting at `chars[start] == '['`, returning it with
+    /// the index of the closing ']'.
+    fn parse_class(chars: &[char], start: usize) -> Option<(Token, usize)> {
+        let mut i = start + 1;
+        let negated = matches!(chars.get(i), Some('!' | '^'));
+        if negated {
+            i += 1;
+        }
+
+        let mut ranges = Vec::new();
+        let first = i;
+        loop {
+            let c = *chars.get(i)?;
+            if c == ']' && i > first {
+                return Some((Token::Class { negated, ranges }, i));
+            }
+            if chars.get(i + 1) == Some(&'-') && chars.get(i + 2).is_some_and(|&e| e != ']') {
+                ranges.push((c, chars[i + 2]));
+                i += 3;
+            } else {
+                ranges.push((c, c));
+                i += 1;
+            }
+        }
+    }
+
+    fn match_tokens(tokens: &[Token], path: &[char]) -> bool {
+        let Some((token, rest)) = tokens.split_first() else {
+            return path.is_empty();
+        };
+        match token {
+            Token::Char(c) => path.first() == Some(c) && Self::match_tokens(rest, &path[1..]),
+            Token::AnyChar => {
+                path.first().is_some_and(|&c| c != '/') && Self::match_tokens(rest, &path[1..])
+            }
+            Token::Class { negated, ranges } => {
+                path.first().is_some_and(|&c| {
+                    c != '/' && ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi) != *negated
+                }) && Self::match_tokens(rest, &path[1..])
+            }
+            Token::AnySeq => {
+                let end = path.iter().position(|&c| c == '/').unwrap_or(path.len());
+                (0..=end).any(|i| Self::match_tokens(rest, &path[i..]))
+            }
+            Token::AnyDirs => {
+                Self::match_tokens(rest, path)
+                    || (0..path.len())
+                        .filter(|&i| path[i] == '/')
+                        .any(|i| Self::match_tokens(rest, &path[i + 1..]))
+            }
+            Token::AnyPath => true,
+        }
+    }
+}
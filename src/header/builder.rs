@@ -1,47 +1,266 @@
-use super::{constants::*, error::HeaderError};
-use std::{fs, os::unix::fs::MetadataExt, path::Path};
+use super::{EntryType, HeaderFormat, ParsedHeader, PaxRecord, constants::*, error::HeaderError};
+use std::time::Duration;
 
 pub struct HeaderBuilder;
 
 impl HeaderBuilder {
-    pub fn build_from_path(path: impl AsRef<Path>) -> Result<[u8; BLOCK_SIZE], HeaderError> {
-        let metadata = fs::metadata(path.as_ref())?;
-        let name = path
-            .as_ref()
-            .file_name()
-            .and_then(|n| n.to_str())
-            .ok_or_else(|| {
-                HeaderError::InvalidFileName(path.as_ref().to_string_lossy().into_owned())
-            })?;
-        Self::build(name, &metadata)
+    /// Serializes `header` into the blocks that precede its data in `format`.
+    pub fn build(header: &ParsedHeader, format: HeaderFormat) -> Result<Vec<u8>, HeaderError> {
+        match format {
+            HeaderFormat::Pax => Self::build_ustar(header),
+            HeaderFormat::Gnu => Self::build_gnu(header),
+        }
     }
 
-    pub fn build(name: &str, metadata: &fs::Metadata) -> Result<[u8; BLOCK_SIZE], HeaderError> {
-        let mut header = [0u8; BLOCK_SIZE];
-        let name_bytes = name.as_bytes();
-        header[NAME_FIELD][..name_bytes.len()].copy_from_slice(name_bytes);
+    /// Serializes `header` into one ustar block, preceded by a pax extended
+    /// header when some field cannot be represented in plain ustar.
+    fn build_ustar(header: &ParsedHeader) -> Result<Vec<u8>, HeaderError> {
+        let mut block = [0u8; BLOCK_SIZE];
+        let mut records = Vec::new();
 
-        Self::write_octal(&mut header[MODE_FIELD], metadata.mode() as u64, 8)?;
-        Self::write_octal(&mut header[UID_FIELD], metadata.uid() as u64, 8)?;
-        Self::write_octal(&mut header[GID_FIELD], metadata.gid() as u64, 8)?;
-        Self::write_octal(&mut header[SIZE_FIELD], metadata.size(), 12)?;
-        Self::write_octal(&mut header[MTIME_FIELD], metadata.mtime() as u64, 12)?;
+        match Self::split_name(&header.name) {
+            Some((prefix, name)) => {
+                Self::write_str(&mut block[PREFIX_FIELD], prefix);
+                Self::write_str(&mut block[NAME_FIELD], name);
+            }
+            None => {
+                records.push(PaxRecord::new("path", header.name.as_str()));
+                Self::write_str(&mut block[NAME_FIELD], &header.name);
+            }
+        }
+        if header.linkname.len() > LINKNAME_FIELD.len() {
+            records.push(PaxRecord::new("linkpath", header.linkname.as_str()));
+        }
+        Self::write_str(&mut block[LINKNAME_FIELD], &header.linkname);
+        for (field, key, value) in [
+            (UNAME_FIELD, "uname", &header.uname),
+            (GNAME_FIELD, "gname", &header.gname),
+        ] {
+            if value.len() > field.len() {
+                records.push(PaxRecord::new(key, value.as_str()));
+            }
+            Self::write_str(&mut block[field], value);
+        }
 
-        header[CHECKSUM_FIELD].fill(b' ');
-        header[TYPEFLAG_FIELD] = TYPEFLAG_REGULAR;
-        header[MAGIC_FIELD].copy_from_slice(USTAR_MAGIC);
-        header[VERSION_FIELD].copy_from_slice(USTAR_VERSION);
+        Self::write_octal(&mut block[MODE_FIELD], header.mode & 0o7777777, 8)?;
+        Self::write_numeric(&mut block[UID_FIELD], header.uid, "uid", &mut records)?;
+        Self::write_numeric(&mut block[GID_FIELD], header.gid, "gid", &mut records)?;
+        Self::write_numeric(&mut block[SIZE_FIELD], header.size, "size", &mut records)?;
+        Self::write_numeric(&mut block[MTIME_FIELD], header.mtime, "mtime", &mut records)?;
+        Self::write_device(&mut block, header)?;
+        if header.mtime_nsec != 0 {
+            records.retain(|r| r.key != "mtime");
+            let mtime = Duration::new(header.mtime, header.mtime_nsec);
+            records.push(PaxRecord::new("mtime", PaxRecord::format_time(mtime)));
+        }
+        // Whole-second access and change times are left out, so that
+        // members described from metadata only get an extended header when
+        // the filesystem keeps sub-second times.
+        for (key, time) in [("atime", header.atime), ("ctime", header.ctime)] {
+            if let Some(time) = time.filter(|t| t.subsec_nanos() != 0) {
+                records.push(PaxRecord::new(key, PaxRecord::format_time(time)));
+            }
+        }
 
-        let checksum = header.iter().map(|&b| b as u32).sum::<u32>();
-        Self::write_octal(&mut header[CHECKSUM_FIELD], checksum as u64, 8)?;
+        block[TYPEFLAG_FIELD] = header.entry_type.as_byte();
+        Self::finish(&mut block, HeaderFormat::Pax)?;
 
-        Ok(header)
+        let mut out = Vec::with_capacity(BLOCK_SIZE);
+        if !records.is_empty() {
+            out.extend(Self::build_pax(header, &records)?);
+        }
+        out.extend_from_slice(&block);
+        Ok(out)
     }
 
+    /// Serializes `header` the way GNU tar does: long paths go into preceding
+    /// `././@LongLink` entries and large numbers use base-256.
+    fn build_gnu(header: &ParsedHeader) -> Result<Vec<u8>, HeaderError> {
+        let mut block = [0u8; BLOCK_SIZE];
+        Self::write_str(&mut block[NAME_FIELD], &header.name);
+        Self::write_str(&mut block[LINKNAME_FIELD], &header.linkname);
+        Self::write_str(&mut block[UNAME_FIELD], &header.uname);
+        Self::write_str(&mut block[GNAME_FIELD], &header.gname);
+        Self::write_octal(&mut block[MODE_FIELD], header.mode & 0o7777777, 8)?;
+        Self::write_octal(&mut block[UID_FIELD], header.uid, 8)?;
+        Self::write_octal(&mut block[GID_FIELD], header.gid, 8)?;
+        Self::write_octal(&mut block[SIZE_FIELD], header.size, 12)?;
+        Self::write_octal(&mut block[MTIME_FIELD], header.mtime, 12)?;
+        Self::write_device(&mut block, header)?;
+        block[TYPEFLAG_FIELD] = header.entry_type.as_byte();
+        Self::finish(&mut block, HeaderFormat::Gnu)?;
+
+        let mut out = Vec::with_capacity(BLOCK_SIZE);
+        if header.linkname.len() > LINKNAME_FIELD.len() {
+            out.extend(Self::build_long_link(
+                &header.linkname,
+                EntryType::GnuLongLink,
+            )?);
+        }
+        if header.name.len() > NAME_FIELD.len() {
+            out.extend(Self::build_long_link(&header.name, EntryType::GnuLongName)?);
+        }
+        out.extend_from_slice(&block);
+        Ok(out)
+    }
+
+    /// Builds a GNU long name ('L') or long link ('K') entry whose data is the
+    /// NUL-terminated path.
+    fn build_long_link(path: &str, entry_type: EntryType) -> Result<Vec<u8>, HeaderError> {
+        let mut data = path.as_bytes().to_vec();
+        data.push(0);
+
+        let mut block = [0u8; BLOCK_SIZE];
+        Self::write_str(&mut block[NAME_FIELD], GNU_LONGLINK_NAME);
+        Self::write_octal(&mut block[MODE_FIELD], 0o644, 8)?;
+        Self::write_octal(&mut block[UID_FIELD], 0, 8)?;
+        Self::write_octal(&mut block[GID_FIELD], 0, 8)?;
+        Self::write_octal(&mut block[SIZE_FIELD], data.len() as u64, 12)?;
+        Self::write_octal(&mut block[MTIME_FIELD], 0, 12)?;
+        block[TYPEFLAG_FIELD] = entry_type.as_byte();
+        Self::finish(&mut block, HeaderFormat::Gnu)?;
+
+        let padding = (BLOCK_SIZE - data.len() % BLOCK_SIZE) % BLOCK_SIZE;
+        let mut out = Vec::with_capacity(BLOCK_SIZE + data.len() + padding);
+        out.extend_from_slice(&block);
+        out.extend(data);
+        out.resize(out.len() + padding, 0);
+        Ok(out)
+    }
+
+    /// Builds a pax extended header entry ('x') carrying `records`, with its
+    /// data padded to a whole number of blocks.
+    fn build_pax(header: &ParsedHeader, records: &[PaxRecord]) -> Result<Vec<u8>, HeaderError> {
+        let data = records.iter().flat_map(|r| r.encode()).collect::<Vec<u8>>();
+        let base_name = header
+            .name
+            .trim_end_matches('/')
+            .rsplit('/')
+            .next()
+            .unwrap_or_default();
+
+        let mut block = [0u8; BLOCK_SIZE];
+        Self::write_str(&mut block[NAME_FIELD], &format!("PaxHeaders/{base_name}"));
+        Self::write_octal(&mut block[MODE_FIELD], 0o644, 8)?;
+        Self::write_octal(&mut block[UID_FIELD], 0, 8)?;
+        Self::write_octal(&mut block[GID_FIELD], 0, 8)?;
+        Self::write_octal(&mut block[SIZE_FIELD], data.len() as u64, 12)?;
+        let mtime = if Self::fits_octal(header.mtime, 12) {
+            header.mtime
+        } else {
+            0
+        };
+        Self::write_octal(&mut block[MTIME_FIELD], mtime, 12)?;
+        block[TYPEFLAG_FIELD] = EntryType::PaxHeader.as_byte();
+        Self::finish(&mut block, HeaderFormat::Pax)?;
+
+        let padding = (BLOCK_SIZE - data.len() % BLOCK_SIZE) % BLOCK_SIZE;
+        let mut out = Vec::with_capacity(BLOCK_SIZE + data.len() + padding);
+        out.extend_from_slice(&block);
+        out.extend(data);
+        out.resize(out.len() + padding, 0);
+        Ok(out)
+    }
+
+    /// Writes magic, version and the checksum, which must come last.
+    fn finish(block: &mut [u8; BLOCK_SIZE], format: HeaderFormat) -> Result<(), HeaderError> {
+        let (magic, version) = match format {
+            HeaderFormat::Pax => (USTAR_MAGIC, USTAR_VERSION),
+            HeaderFormat::Gnu => (GNU_MAGIC, GNU_VERSION),
+        };
+        block[MAGIC_FIELD].copy_from_slice(magic);
+        block[VERSION_FIELD].copy_from_slice(version);
+        block[CHECKSUM_FIELD].fill(b' ');
+        let checksum = block.iter().map(|&b| b as u32).sum::<u32>();
+        Self::write_octal(&mut block[CHECKSUM_FIELD], checksum as u64, 8)
+    }
+
+    /// Splits a path into the ustar `prefix` and `name` fields. Names that do
+    /// not fit in 100 bytes are divided at a `/` so that the prefix holds at
+    /// most 155 bytes; the separating slash itself is not stored. Returns
+    /// `None` when no such split exists.
+    fn split_name(path: &str) -> Option<(&str, &str)> {
+        let name_len = NAME_FIELD.len();
+        let prefix_len = PREFIX_FIELD.len();
+        if path.len() <= name_len {
+            return Some(("", path));
+        }
+
+        // A trailing slash on directories belongs to the name part.
+        let search = path.strip_suffix('/').unwrap_or(path);
+        search
+            .match_indices('/')
+            .map(|(i, _)| i)
+            .find(|&i| i > 0 && i <= prefix_len && path.len() - i - 1 <= name_len)
+            .map(|i| (&path[..i], &path[i + 1..]))
+    }
+
+    /// Fills devmajor and devminor for device entries; other entries leave
+    /// the fields empty, as GNU tar does.
+    fn write_device(
+        block: &mut [u8; BLOCK_SIZE],
+        header: &ParsedHeader,
+    ) -> Result<(), HeaderError> {
+        if header.entry_type.is_device() {
+            Self::write_octal(&mut block[DEVMAJOR_FIELD], header.devmajor, 8)?;
+            Self::write_octal(&mut block[DEVMINOR_FIELD], header.devminor, 8)?;
+        }
+        Ok(())
+    }
+
+    /// Copies as much of `value` as fits; callers record the full value in a
+    /// pax record when it is truncated.
+    fn write_str(dst: &mut [u8], value: &str) {
+        let mut len = value.len().min(dst.len());
+        while !value.is_char_boundary(len) {
+            len -= 1;
+        }
+        dst[..len].copy_from_slice(&value.as_bytes()[..len]);
+    }
+
+    /// Writes `value` as octal, falling back to a pax record (and a zero
+    /// placeholder) when it needs more digits than the field provides.
+    fn write_numeric(
+        dst: &mut [u8],
+        value: u64,
+        key: &str,
+        records: &mut Vec<PaxRecord>,
+    ) -> Result<(), HeaderError> {
+        let len = dst.len();
+        if Self::fits_octal(value, len) {
+            return Self::write_octal(dst, value, len);
+        }
+        records.push(PaxRecord::new(key, value.to_string()));
+        Self::write_octal(dst, 0, len)
+    }
+
+    fn fits_octal(value: u64, len: usize) -> bool {
+        value < 1u64 << (3 * (len - 1))
+    }
+
+    /// Writes `value` as NUL-terminated octal, or as GNU/star base-256 (high
+    /// bit of the first byte set, big-endian value in the rest) when it needs
+    /// more digits than the field provides.
     fn write_octal(dst: &mut [u8], value: u64, len: usize) -> Result<(), HeaderError> {
+        if !Self::fits_octal(value, len) {
+            return Self::write_base256(dst, value, len);
+        }
         let s = format!("{:0len$o}", value, len = len - 1);
         dst[..s.len()].copy_from_slice(s.as_bytes());
         dst[s.len()] = b'\0';
         Ok(())
     }
+
+    fn write_base256(dst: &mut [u8], value: u64, len: usize) -> Result<(), HeaderError> {
+        let bytes = value.to_be_bytes();
+        let digits = len - 1;
+        if digits < bytes.len() && value >> (8 * digits) != 0 {
+            return Err(HeaderError::NumericOverflow);
+        }
+        dst[..len].fill(0);
+        let start = len.saturating_sub(bytes.len());
+        dst[start..len].copy_from_slice(&bytes[bytes.len() - (len - start)..]);
+        dst[0] |= 0x80;
+        Ok(())
+    }
 }
@@ -8,11 +8,33 @@ pub const SIZE_FIELD: Range<usize> = 124..136;
 pub const MTIME_FIELD: Range<usize> = 136..148;
 pub const CHECKSUM_FIELD: Range<usize> = 148..156;
 pub const TYPEFLAG_FIELD: usize = 156;
+pub const LINKNAME_FIELD: Range<usize> = 157..257;
 pub const MAGIC_FIELD: Range<usize> = 257..263;
 pub const VERSION_FIELD: Range<usize> = 263..265;
+pub const UNAME_FIELD: Range<usize> = 265..297;
+pub const GNAME_FIELD: Range<usize> = 297..329;
+pub const DEVMAJOR_FIELD: Range<usize> = 329..337;
+pub const DEVMINOR_FIELD: Range<usize> = 337..345;
+pub const PREFIX_FIELD: Range<usize> = 345..500;
 pub const USTAR_MAGIC: &[u8; 6] = b"ustar\0";
 pub const USTAR_VERSION: &[u8; 2] = b"00";
+pub const GNU_MAGIC: &[u8; 6] = b"ustar ";
+pub const GNU_VERSION: &[u8; 2] = b" \0";
+pub const GNU_LONGLINK_NAME: &str = "././@LongLink";
 pub const TYPEFLAG_REGULAR: u8 = b'0';
+pub const TYPEFLAG_HARD_LINK: u8 = b'1';
+pub const TYPEFLAG_SYMLINK: u8 = b'2';
+pub const TYPEFLAG_CHAR_DEVICE: u8 = b'3';
+pub const TYPEFLAG_BLOCK_DEVICE: u8 = b'4';
+pub const TYPEFLAG_DIRECTORY: u8 = b'5';
+pub const TYPEFLAG_FIFO: u8 = b'6';
+pub const TYPEFLAG_PAX_HEADER: u8 = b'x';
+pub const TYPEFLAG_PAX_GLOBAL: u8 = b'g';
+pub const TYPEFLAG_GNU_LONGNAME: u8 = b'L';
+pub const TYPEFLAG_GNU_LONGLINK: u8 = b'K';
 pub const BLOCK_SIZE: usize = 512;
 pub const END_MARKER_BLOCKS: usize = 2;
+/// Largest pax or GNU long name payload accepted when reading, so that a
+/// corrupt size field cannot make the reader buffer gigabytes.
+pub const MAX_EXTENSION_SIZE: u64 = 1 << 20;
 
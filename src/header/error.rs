@@ -4,6 +4,9 @@ use std::{fmt::Display, io, num::ParseIntError, str::Utf8Error};
 #[derive(Debug)]
 pub enum HeaderError {
     InvalidFileName(String),
+    UnsupportedFileType(String),
+    InvalidPaxRecord,
+    NumericOverflow,
     IntConversion(ParseIntError),
     ChecksumMisatch,
     InvalidHeaderFormat,
@@ -15,6 +18,9 @@ impl Display for HeaderError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::InvalidFileName(name) => write!(f, "invalid file name {name}"),
+            Self::UnsupportedFileType(name) => write!(f, "unsupported file type for {name}"),
+            Self::InvalidPaxRecord => write!(f, "malformed pax extended header record"),
+            Self::NumericOverflow => write!(f, "numeric header field out of range"),
             Self::IntConversion(e) => write!(f, "parse error {e}"),
             Self::ChecksumMisatch => {
                 write!(f, "header checksum mismatch - possibly corrupted ")
@@ -42,3 +48,5 @@ impl From<ParseIntError> for HeaderError {
         Self::IntConversion(value)
     }
 }
+
+impl std::error::Error for HeaderError {}
@@ -2,14 +2,111 @@ mod builder;
 pub mod constants;
 mod error;
 mod parser;
+mod pax;
 mod validator;
 
 
 pub use error::HeaderError;
 pub use builder::HeaderBuilder;
 pub use parser::HeaderParser;
+pub use pax::PaxRecord;
 pub use validator::HeaderValidator;
 
+use crate::sys;
+use constants::*;
+use std::{
+    fmt, fs,
+    os::unix::fs::{FileTypeExt, MetadataExt},
+    time::Duration,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryType {
+    Regular,
+    HardLink,
+    Symlink,
+    CharDevice,
+    BlockDevice,
+    Directory,
+    Fifo,
+    PaxHeader,
+    PaxGlobalHeader,
+    GnuLongName,
+    GnuLongLink,
+    Other(u8),
+}
+
+impl EntryType {
+    pub fn from_byte(flag: u8) -> Self {
+        match flag {
+            TYPEFLAG_REGULAR | b'\0' => Self::Regular,
+            TYPEFLAG_HARD_LINK => Self::HardLink,
+            TYPEFLAG_SYMLINK => Self::Symlink,
+            TYPEFLAG_CHAR_DEVICE => Self::CharDevice,
+            TYPEFLAG_BLOCK_DEVICE => Self::BlockDevice,
+            TYPEFLAG_DIRECTORY => Self::Directory,
+            TYPEFLAG_FIFO => Self::Fifo,
+            TYPEFLAG_PAX_HEADER => Self::PaxHeader,
+            TYPEFLAG_PAX_GLOBAL => Self::PaxGlobalHeader,
+            TYPEFLAG_GNU_LONGNAME => Self::GnuLongName,
+            TYPEFLAG_GNU_LONGLINK => Self::GnuLongLink,
+            other => Self::Other(other),
+        }
+    }
+
+    /// Device nodes carry major and minor numbers in their header.
+    pub fn is_device(&self) -> bool {
+        matches!(self, Self::CharDevice | Self::BlockDevice)
+    }
+
+    pub fn as_byte(&self) -> u8 {
+        match self {
+            Self::Regular => TYPEFLAG_REGULAR,
+            Self::HardLink => TYPEFLAG_HARD_LINK,
+            Self::Symlink => TYPEFLAG_SYMLINK,
+            Self::CharDevice => TYPEFLAG_CHAR_DEVICE,
+            Self::BlockDevice => TYPEFLAG_BLOCK_DEVICE,
+            Self::Directory => TYPEFLAG_DIRECTORY,
+            Self::Fifo => TYPEFLAG_FIFO,
+            Self::PaxHeader => TYPEFLAG_PAX_HEADER,
+            Self::PaxGlobalHeader => TYPEFLAG_PAX_GLOBAL,
+            Self::GnuLongName => TYPEFLAG_GNU_LONGNAME,
+            Self::GnuLongLink => TYPEFLAG_GNU_LONGLINK,
+            Self::Other(flag) => *flag,
+        }
+    }
+}
+
+impl fmt::Display for EntryType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Regular => write!(f, "regular"),
+            Self::HardLink => write!(f, "hard_link"),
+            Self::Symlink => write!(f, "symlink"),
+            Self::CharDevice => write!(f, "char_device"),
+            Self::BlockDevice => write!(f, "block_device"),
+            Self::Directory => write!(f, "directory"),
+            Self::Fifo => write!(f, "fifo"),
+            Self::PaxHeader => write!(f, "pax_header"),
+            Self::PaxGlobalHeader => write!(f, "pax_global_header"),
+            Self::GnuLongName => write!(f, "gnu_long_name"),
+            Self::GnuLongLink => write!(f, "gnu_long_link"),
+            Self::Other(flag) => write!(f, "other({})", flag.escape_ascii()),
+        }
+    }
+}
+
+/// How entries that do not fit plain ustar fields are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HeaderFormat {
+    /// POSIX ustar, adding pax extended headers only for oversized fields.
+    #[default]
+    Pax,
+    /// GNU tar: `././@LongLink` entries for long paths, base-256 numbers.
+    Gnu,
+}
+
+#[derive(Debug, Clone)]
 pub struct ParsedHeader {
     pub name: String,
     pub size: u64,
@@ -17,4 +114,108 @@ pub struct ParsedHeader {
     pub uid: u64,
     pub gid: u64,
     pub mtime: u64,
+    pub entry_type: EntryType,
+    pub linkname: String,
+    /// Owner and group names; empty when the archive does not record them.
+    pub uname: String,
+    pub gname: String,
+    /// Device numbers of character and block device entries.
+    pub devmajor: u64,
+    pub devminor: u64,
+    /// Sub-second part of `mtime`, only representable through pax records.
+    pub mtime_nsec: u32,
+    pub atime: Option<Duration>,
+    pub ctime: Option<Duration>,
+}
+
+impl ParsedHeader {
+    /// Starts a header for an entry built in memory, e.g. for
+    /// `Builder::append_data`. Ownership and mtime default to zero.
+    pub fn new(name: &str, entry_type: EntryType, size: u64) -> Self {
+        let mode = match entry_type {
+            EntryType::Directory => 0o755,
+            EntryType::Symlink => 0o777,
+            _ => 0o644,
+        };
+        Self {
+            name: name.to_string(),
+            size,
+            mode,
+            uid: 0,
+            gid: 0,
+            mtime: 0,
+            entry_type,
+            linkname: String::new(),
+            uname: String::new(),
+            gname: String::new(),
+            devmajor: 0,
+            devminor: 0,
+            mtime_nsec: 0,
+            atime: None,
+            ctime: None,
+        }
+    }
+
+    /// Describes a filesystem object as an archive entry. `metadata` should come
+    /// from `symlink_metadata` so that links are stored as links; the caller
+    /// fills in `linkname` for symbolic and hard links. Owner names come from
+    /// the local passwd and group databases.
+    pub fn from_metadata(name: &str, metadata: &fs::Metadata) -> Result<Self, HeaderError> {
+        Self::from_metadata_with_owners(name, metadata, &mut sys::OwnerCache::default())
+    }
+
+    /// Like `from_metadata`, but looks owner names up through `owners` so
+    /// that callers describing many files resolve each owner only once.
+    pub(crate) fn from_metadata_with_owners(
+        name: &str,
+        metadata: &fs::Metadata,
+        owners: &mut sys::OwnerCache,
+    ) -> Result<Self, HeaderError> {
+        let file_type = metadata.file_type();
+        let (entry_type, size) = if file_type.is_dir() {
+            (EntryType::Directory, 0)
+        } else if file_type.is_symlink() {
+            (EntryType::Symlink, 0)
+        } else if file_type.is_file() {
+            (EntryType::Regular, metadata.size())
+        } else if file_type.is_char_device() {
+            (EntryType::CharDevice, 0)
+        } else if file_type.is_block_device() {
+            (EntryType::BlockDevice, 0)
+        } else if file_type.is_fifo() {
+            (EntryType::Fifo, 0)
+        } else {
+            return Err(HeaderError::UnsupportedFileType(name.to_string()));
+        };
+
+        let name = match entry_type {
+            EntryType::Directory if !name.ends_with('/') => format!("{name}/"),
+            _ => name.to_string(),
+        };
+        let (devmajor, devminor) = match entry_type {
+            EntryType::CharDevice | EntryType::BlockDevice => sys::split_dev(metadata.rdev()),
+            _ => (0, 0),
+        };
+
+        Ok(Self {
+            mode: (metadata.mode() & 0o7777) as u64,
+            uid: metadata.uid() as u64,
+            gid: metadata.gid() as u64,
+            uname: owners.user_name(metadata.uid()).unwrap_or_default(),
+            gname: owners.group_name(metadata.gid()).unwrap_or_default(),
+            devmajor,
+            devminor,
+            mtime: metadata.mtime() as u64,
+            mtime_nsec: metadata.mtime_nsec() as u32,
+            atime: Some(Duration::new(
+                metadata.atime().max(0) as u64,
+                metadata.atime_nsec() as u32,
+            )),
+            ctime: Some(Duration::new(
+                metadata.ctime().max(0) as u64,
+                metadata.ctime_nsec() as u32,
+            )),
+            ..Self::new(&name, entry_type, size)
+        })
+    }
 }
@@ -1,18 +1,40 @@
-use super::{ParsedHeader, constants::*, error::HeaderError};
+use super::{EntryType, ParsedHeader, PaxRecord, constants::*, error::HeaderError};
 use std::str::from_utf8;
 pub struct HeaderParser;
 
 impl HeaderParser {
     pub fn parse(header: &[u8]) -> Result<ParsedHeader, HeaderError> {
-        let name = from_utf8(&header[NAME_FIELD])?
-            .trim_end_matches('\0')
-            .to_string();
+        let mut name = Self::read_str(&header[NAME_FIELD])?;
+        if &header[MAGIC_FIELD] == USTAR_MAGIC {
+            let prefix = Self::read_str(&header[PREFIX_FIELD])?;
+            if !prefix.is_empty() {
+                name = format!("{prefix}/{name}");
+            }
+        }
+        let linkname = Self::read_str(&header[LINKNAME_FIELD])?;
+        // Pre-POSIX headers keep nothing but padding past the link name.
+        let (uname, gname) = match &header[MAGIC_FIELD] {
+            magic if magic == USTAR_MAGIC || magic == GNU_MAGIC => (
+                Self::read_str(&header[UNAME_FIELD])?,
+                Self::read_str(&header[GNAME_FIELD])?,
+            ),
+            _ => (String::new(), String::new()),
+        };
 
         let mode = Self::read_octal(&header[MODE_FIELD])?;
         let uid = Self::read_octal(&header[UID_FIELD])?;
         let gid = Self::read_octal(&header[GID_FIELD])?;
-        let size = Self::read_octal(&header[SIZE_FIELD])?;
+        let size = Self::parse_size(header)?;
         let mtime = Self::read_octal(&header[MTIME_FIELD])?;
+        let entry_type = EntryType::from_byte(header[TYPEFLAG_FIELD]);
+        let (devmajor, devminor) = if entry_type.is_device() {
+            (
+                Self::read_octal(&header[DEVMAJOR_FIELD])?,
+                Self::read_octal(&header[DEVMINOR_FIELD])?,
+            )
+        } else {
+            (0, 0)
+        };
 
         Ok(ParsedHeader {
             name,
@@ -21,16 +43,111 @@ impl HeaderParser {
             gid,
             uid,
             mtime,
+            entry_type,
+            linkname,
+            uname,
+            gname,
+            devmajor,
+            devminor,
+            mtime_nsec: 0,
+            atime: None,
+            ctime: None,
         })
     }
 
+    /// Extracts the path carried in the data of a GNU `././@LongLink` entry.
+    pub fn parse_long_name(data: &[u8]) -> Result<String, HeaderError> {
+        Self::read_str(data)
+    }
+
+    pub fn parse_pax(data: &[u8]) -> Result<Vec<PaxRecord>, HeaderError> {
+        PaxRecord::parse_all(data)
+    }
+
+    /// Returns the member size carried by pax records, if any. It replaces
+    /// the ustar size field, which holds 0 for members of 8 GiB or more.
+    pub fn pax_size(records: &[PaxRecord]) -> Result<Option<u64>, HeaderError> {
+        let mut size = None;
+        for record in records.iter().filter(|r| r.key == "size") {
+            size = Some(Self::check_size(record.value.parse()?)?);
+        }
+        Ok(size)
+    }
+
+    /// Overrides header fields with values from pax records. Global records
+    /// should be applied first so that per-entry records take precedence.
+    pub fn apply_pax(header: &mut ParsedHeader, records: &[PaxRecord]) -> Result<(), HeaderError> {
+        for record in records {
+            let value = record.value.as_str();
+            match record.key.as_str() {
+                "path" => header.name = value.to_string(),
+                "linkpath" => header.linkname = value.to_string(),
+                "size" => header.size = Self::check_size(value.parse()?)?,
+                "uid" => header.uid = value.parse()?,
+                "gid" => header.gid = value.parse()?,
+                "uname" => header.uname = value.to_string(),
+                "gname" => header.gname = value.to_string(),
+                "mtime" => {
+                    let mtime = PaxRecord::parse_time(value)?;
+                    header.mtime = mtime.as_secs();
+                    header.mtime_nsec = mtime.subsec_nanos();
+                }
+                "atime" => header.atime = Some(PaxRecord::parse_time(value)?),
+                "ctime" => header.ctime = Some(PaxRecord::parse_time(value)?),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
     pub fn parse_size(header: &[u8]) -> Result<u64, HeaderError> {
-        Self::read_octal(&header[SIZE_FIELD])
+        Self::check_size(Self::read_octal(&header[SIZE_FIELD])?)
     }
 
+    /// Refuses member sizes whose data, padded to a whole block, would not
+    /// fit in a u64 stream offset. Only base-256 and pax sizes get this big.
+    fn check_size(size: u64) -> Result<u64, HeaderError> {
+        if size > u64::MAX - (BLOCK_SIZE as u64 - 1) {
+            return Err(HeaderError::NumericOverflow);
+        }
+        Ok(size)
+    }
+
+    pub fn parse_checksum(header: &[u8]) -> Result<u64, HeaderError> {
+        Self::read_octal(&header[CHECKSUM_FIELD])
+    }
+
+    fn read_str(src: &[u8]) -> Result<String, HeaderError> {
+        let end = src.iter().position(|&b| b == 0).unwrap_or(src.len());
+        Ok(from_utf8(&src[..end])?.to_string())
+    }
+
+    /// Decodes a numeric field, which is either NUL/space terminated octal or,
+    /// when the high bit of the first byte is set, GNU base-256 big-endian.
     fn read_octal(src: &[u8]) -> Result<u64, HeaderError> {
-        let s = from_utf8(src)?.trim_end_matches('\0').trim();
+        if src.first().is_some_and(|&b| b & 0x80 != 0) {
+            return Self::read_base256(src);
+        }
+        let s = from_utf8(src)?.trim_matches(|c| c == '\0' || c == ' ');
+        if s.is_empty() {
+            return Ok(0);
+        }
         let v = u64::from_str_radix(s, 8)?;
         Ok(v)
     }
+
+    fn read_base256(src: &[u8]) -> Result<u64, HeaderError> {
+        // 0xff marks a negative value, which no field we use can hold.
+        if src[0] == 0xff {
+            return Err(HeaderError::NumericOverflow);
+        }
+        let mut value = (src[0] & 0x7f) as u64;
+        for &b in &src[1..] {
+            if value > u64::MAX >> 8 {
+                return Err(HeaderError::NumericOverflow);
+            }
+            value = (value << 8) | b as u64;
+        }
+        Ok(value)
+    }
 }
@@ -0,0 +1,79 @@
+use super::error::HeaderError;
+use std::{str::from_utf8, time::Duration};
+
+/// A single `length key=value\n` record from a pax extended header.
+#[derive(Debug, Clone)]
+pub struct PaxRecord {
+    pub key: String,
+    pub value: String,
+}
+
+impl PaxRecord {
+    pub fn new(key: &str, value: impl Into<String>) -> Self {
+        Self {
+            key: key.to_string(),
+            value: value.into(),
+        }
+    }
+
+    pub fn parse_all(data: &[u8]) -> Result<Vec<Self>, HeaderError> {
+        let mut records = Vec::new();
+        let mut rest = data;
+        while !rest.is_empty() && rest[0] != 0 {
+            let space = rest
+                .iter()
+                .position(|&b| b == b' ')
+                .ok_or(HeaderError::InvalidPaxRecord)?;
+            let len = from_utf8(&rest[..space])?.parse::<usize>()?;
+            if len <= space + 1 || len > rest.len() || rest[len - 1] != b'\n' {
+                return Err(HeaderError::InvalidPaxRecord);
+            }
+            let record = from_utf8(&rest[space + 1..len - 1])?;
+            let (key, value) = record
+                .split_once('=')
+                .ok_or(HeaderError::InvalidPaxRecord)?;
+            records.push(Self::new(key, value));
+            rest = &rest[len..];
+        }
+        Ok(records)
+    }
+
+    /// Encodes the record; the leading length counts its own digits.
+    pub fn encode(&self) -> Vec<u8> {
+        let body = format!(" {}={}\n", self.key, self.value);
+        let mut len = body.len() + 1;
+        loop {
+            let total = len.to_string().len() + body.len();
+            if total == len {
+                break;
+            }
+            len = total;
+        }
+        format!("{len}{body}").into_bytes()
+    }
+
+    pub fn format_time(time: Duration) -> String {
+        if time.subsec_nanos() == 0 {
+            return time.as_secs().to_string();
+        }
+        let nanos = format!("{:09}", time.subsec_nanos());
+        format!("{}.{}", time.as_secs(), nanos.trim_end_matches('0'))
+    }
+
+    /// Parses a pax timestamp such as `1700000000.123456789`. Times before the
+    /// epoch are clamped to zero.
+    pub fn parse_time(value: &str) -> Result<Duration, HeaderError> {
+        if value.starts_with('-') {
+            return Ok(Duration::ZERO);
+        }
+        let (secs, fraction) = value.split_once('.').unwrap_or((value, ""));
+        let secs = secs.parse::<u64>()?;
+        let digits = &fraction[..fraction.len().min(9)];
+        let nanos = if digits.is_empty() {
+            0
+        } else {
+            digits.parse::<u32>()? * 10u32.pow(9 - digits.len() as u32)
+        };
+        Ok(Duration::new(secs, nanos))
+    }
+}
@@ -1,4 +1,4 @@
-use super::{constants::*, error::HeaderError};
+use super::{HeaderParser, constants::*, error::HeaderError};
 
 pub struct HeaderValidator;
 
@@ -9,21 +9,24 @@ impl HeaderValidator {
         Ok(())
     }
     fn validate_magic(header: &[u8]) -> Result<(), HeaderError> {
-        if &header[MAGIC_FIELD] != USTAR_MAGIC || &header[VERSION_FIELD] != USTAR_VERSION {
+        let magic = (&header[MAGIC_FIELD], &header[VERSION_FIELD]);
+        let is_ustar = magic == (USTAR_MAGIC, USTAR_VERSION);
+        // GNU tar writes the pre-POSIX "ustar  \0" signature.
+        let is_gnu = magic == (GNU_MAGIC, GNU_VERSION);
+        if !is_ustar && !is_gnu {
             return Err(HeaderError::InvalidHeaderFormat);
         }
         Ok(())
     }
     pub fn validate_checksum(header: &[u8]) -> Result<(), HeaderError> {
-        let file_checksum = header[CHECKSUM_FIELD]
+        let file_checksum = HeaderParser::parse_checksum(header)?;
+        let field_sum = header[CHECKSUM_FIELD]
             .iter()
             .map(|&b| b as u64)
             .sum::<u64>();
-        let actual_checksum = header.iter().map(|&b| b as u64).sum::<u64>()
-            - header[CHECKSUM_FIELD]
-                .iter()
-                .map(|&b| b as u64)
-                .sum::<u64>();
+        // The checksum is computed as if the checksum field itself were all spaces.
+        let actual_checksum = header.iter().map(|&b| b as u64).sum::<u64>() - field_sum
+            + CHECKSUM_FIELD.len() as u64 * b' ' as u64;
         if file_checksum != actual_checksum {
             return Err(HeaderError::ChecksumMisatch);
         }
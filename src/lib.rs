@@ -1,10 +1,19 @@
-use archive::Archiver;
-use clap::{Parser, Subcommand};
-use std::path::Path;
+use archive::{Codec, InputPath, ListFormat, OverwritePolicy, WalkFilter};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use glob::{GlobError, Pattern};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
-mod archive;
-mod header;
-mod validation;
+pub mod archive;
+pub mod glob;
+pub mod header;
+mod sys;
+pub mod validation;
+
+pub use archive::{Archive, ArchiveEntry, Archiver, ArchiverError, Builder};
+pub use header::{EntryType, HeaderFormat, ParsedHeader};
 
 type TarResult<T> = Result<T, Box<dyn std::error::Error>>;
 
@@ -17,37 +26,408 @@ pub struct Cli {
 
 #[derive(Subcommand)]
 enum Command {
-    Create { archive: String, files: Vec<String> },
-    List { archive: String },
-    Append { archive: String, files: Vec<String> },
-    Extract { archive: String, output_dir: String },
+    Create {
+        archive: String,
+        files: Vec<String>,
+        /// How to store paths and numbers that do not fit ustar fields
+        #[arg(long, value_enum, default_value_t = Format::Pax)]
+        format: Format,
+        #[command(flatten)]
+        compression: CompressionArgs,
+        #[command(flatten)]
+        selection: SelectionArgs,
+    },
+    List {
+        archive: String,
+        /// Show permissions, owner, size and modification time
+        #[arg(short, long)]
+        verbose: bool,
+        /// Show owners as numeric uid/gid
+        #[arg(long)]
+        numeric_owner: bool,
+        /// Output format; json, jsonl and csv include every field and offset
+        #[arg(long, value_enum, default_value_t = ListingFormat::Text)]
+        format: ListingFormat,
+    },
+    Append {
+        archive: String,
+        files: Vec<String>,
+        #[arg(long, value_enum, default_value_t = Format::Pax)]
+        format: Format,
+        #[command(flatten)]
+        selection: SelectionArgs,
+        #[command(flatten)]
+        recompress: RecompressArgs,
+    },
+    /// Append only files that are newer than their archived copy
+    Update {
+        archive: String,
+        files: Vec<String>,
+        #[arg(long, value_enum, default_value_t = Format::Pax)]
+        format: Format,
+        #[command(flatten)]
+        selection: SelectionArgs,
+        #[command(flatten)]
+        recompress: RecompressArgs,
+    },
+    /// Compare members with the files they would be extracted to
+    #[command(visible_alias = "compare")]
+    Diff {
+        archive: String,
+        /// Directory to compare against
+        #[arg(short = 'C', long, default_value = ".")]
+        directory: PathBuf,
+        /// Keep leading '/' and '..' in member names instead of refusing them
+        #[arg(short = 'P', long)]
+        absolute_names: bool,
+    },
+    /// Append the members of other archives to an archive
+    #[command(visible_alias = "catenate")]
+    Concat {
+        archive: String,
+        #[arg(required = true)]
+        sources: Vec<String>,
+        #[command(flatten)]
+        recompress: RecompressArgs,
+    },
+    /// Remove members from an archive
+    Delete {
+        archive: String,
+        /// Members to remove; globs and directory names are allowed
+        #[arg(required = true, value_parser = Pattern::new)]
+        members: Vec<Pattern>,
+        #[command(flatten)]
+        recompress: RecompressArgs,
+    },
+    Extract {
+        archive: String,
+        output_dir: String,
+        /// Extract only these members; globs and directory names are allowed
+        #[arg(value_parser = Pattern::new)]
+        members: Vec<Pattern>,
+        /// Skip members matching this pattern at any depth
+        #[arg(long, value_parser = Pattern::new)]
+        exclude: Vec<Pattern>,
+        /// Keep leading '/' and '..' in member names instead of refusing them
+        #[arg(short = 'P', long)]
+        absolute_names: bool,
+        /// Restore archived ownership (default when running as root)
+        #[arg(long, overrides_with = "no_same_owner")]
+        same_owner: bool,
+        /// Extract files as the current user
+        #[arg(long)]
+        no_same_owner: bool,
+        /// Restore ownership by archived uid/gid rather than owner names
+        #[arg(long)]
+        numeric_owner: bool,
+        /// Permission bits to clear, in octal (default: the process umask)
+        #[arg(long, value_parser = parse_octal)]
+        umask: Option<u32>,
+        /// What to do with files that already exist
+        #[arg(long, value_enum, default_value_t = IfExists::Refuse)]
+        if_exists: IfExists,
+    },
+}
+
+#[derive(Args)]
+struct CompressionArgs {
+    /// Compress the archive with gzip
+    #[arg(short = 'z', long, group = "codec")]
+    gzip: bool,
+    /// Compress the archive with bzip2
+    #[arg(short = 'j', long, group = "codec")]
+    bzip2: bool,
+    /// Compress the archive with xz
+    #[arg(short = 'J', long, group = "codec")]
+    xz: bool,
+    /// Compress the archive with zstd
+    #[arg(long, group = "codec")]
+    zstd: bool,
+    /// Compression level (gzip and xz 0-9, bzip2 1-9, zstd 1-22)
+    #[arg(long)]
+    level: Option<i32>,
+}
+
+/// Options for commands that rewrite an existing compressed archive.
+#[derive(Args)]
+struct RecompressArgs {
+    /// Compression level for the rewritten archive (default: the level it
+    /// was written with, where the format records it)
+    #[arg(long)]
+    level: Option<i32>,
+}
+
+impl CompressionArgs {
+    /// The explicitly requested codec; `Codec::None` leaves the choice to the
+    /// archive extension.
+    fn codec(&self) -> Codec {
+        if self.gzip {
+            Codec::Gzip
+        } else if self.bzip2 {
+            Codec::Bzip2
+        } else if self.xz {
+            Codec::Xz
+        } else if self.zstd {
+            Codec::Zstd
+        } else {
+            Codec::None
+        }
+    }
+}
+
+/// Which files `create`, `append` and `update` pick up. File arguments themselves may
+/// be glob patterns, which rustar expands.
+#[derive(Args)]
+struct SelectionArgs {
+    /// Leave out files matching this pattern at any depth
+    #[arg(long, value_parser = Pattern::new)]
+    exclude: Vec<Pattern>,
+    /// Read exclude patterns from this file, one per line
+    #[arg(long, value_name = "FILE")]
+    exclude_from: Vec<PathBuf>,
+    /// Honour .gitignore-style rules in files with this name in every directory
+    #[arg(long, value_name = "NAME")]
+    exclude_ignore: Vec<String>,
+    /// Honour .gitignore files
+    #[arg(long)]
+    exclude_vcs_ignores: bool,
+}
+
+impl SelectionArgs {
+    fn filter(&self) -> Result<WalkFilter, ArchiverError> {
+        let mut filter = WalkFilter::new();
+        for pattern in &self.exclude {
+            filter.add_exclude(pattern.clone());
+        }
+        for path in &self.exclude_from {
+            filter.add_exclude_from(path)?;
+        }
+        for name in &self.exclude_ignore {
+            filter.add_ignore_file(name);
+        }
+        if self.exclude_vcs_ignores {
+            filter.add_ignore_file(".gitignore");
+        }
+        Ok(filter)
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Pax,
+    Gnu,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum IfExists {
+    /// Leave the file and fail once extraction finishes
+    Refuse,
+    /// Replace the file, rewriting regular files in place
+    Overwrite,
+    /// Leave the file
+    Skip,
+    /// Replace the file unless it is newer than the archived copy
+    KeepNewer,
+    /// Remove the file before extracting
+    UnlinkFirst,
+    /// Rename the file to NAME.~N~ before extracting
+    Backup,
+}
+
+impl From<IfExists> for OverwritePolicy {
+    fn from(policy: IfExists) -> Self {
+        match policy {
+            IfExists::Refuse => OverwritePolicy::Refuse,
+            IfExists::Overwrite => OverwritePolicy::Overwrite,
+            IfExists::Skip => OverwritePolicy::Skip,
+            IfExists::KeepNewer => OverwritePolicy::KeepNewer,
+            IfExists::UnlinkFirst => OverwritePolicy::UnlinkFirst,
+            IfExists::Backup => OverwritePolicy::Backup,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ListingFormat {
+    Text,
+    Json,
+    Jsonl,
+    Csv,
+}
+
+impl From<ListingFormat> for ListFormat {
+    fn from(format: ListingFormat) -> Self {
+        match format {
+            ListingFormat::Text => ListFormat::Text,
+            ListingFormat::Json => ListFormat::Json,
+            ListingFormat::Jsonl => ListFormat::JsonLines,
+            ListingFormat::Csv => ListFormat::Csv,
+        }
+    }
+}
+
+impl From<Format> for HeaderFormat {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::Pax => HeaderFormat::Pax,
+            Format::Gnu => HeaderFormat::Gnu,
+        }
+    }
+}
+
+/// Expands glob arguments. Paths that exist are taken literally, even if
+/// they contain glob characters, and only other arguments are expanded.
+/// Matches are named like any other path, after their final component.
+fn expand_inputs(files: &[String]) -> Result<Vec<InputPath>, ArchiverError> {
+    let mut inputs = Vec::new();
+    for file in files {
+        if fs::symlink_metadata(file).is_ok() {
+            inputs.push(InputPath::new(file));
+            continue;
+        }
+        match glob::expand(file) {
+            Ok(paths) => inputs.extend(paths.into_iter().map(InputPath::from)),
+            // Neither a file nor a valid pattern: let archiving report the
+            // missing file.
+            Err(GlobError::UnclosedClass(_) | GlobError::TrailingEscape(_)) => {
+                inputs.push(InputPath::new(file))
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(inputs)
+}
+
+fn parse_octal(value: &str) -> Result<u32, String> {
+    u32::from_str_radix(value, 8)
+        .ok()
+        .filter(|mode| *mode <= 0o7777)
+        .ok_or_else(|| format!("'{}' is not an octal mode", value))
 }
 
 pub fn run() -> TarResult<()> {
     let cli = Cli::parse();
-    let allowed_extensions = vec!["tar".to_string(), "rustar".to_string()];
-    let archiver = Archiver::new(allowed_extensions);
+    let mut archiver = Archiver::default();
 
     match cli.command {
-        Command::Create { archive, files } => {
+        Command::Create {
+            archive,
+            files,
+            format,
+            compression,
+            selection,
+        } => {
             let archive = Path::new(&archive);
-            archiver.create(archive, files)?;
+            let files = expand_inputs(&files)?;
+            archiver
+                .set_format(format.into())
+                .set_filter(selection.filter()?)
+                .set_codec(compression.codec())
+                .set_compression_level(compression.level);
+            for note in archiver.create(archive, files)? {
+                eprintln!("{}", note);
+            }
         }
-        Command::List { archive } => {
+        Command::List {
+            archive,
+            verbose,
+            numeric_owner,
+            format,
+        } => {
             let archive = Path::new(&archive);
-            archiver.list(archive)?;
+            archiver
+                .set_verbose(verbose)
+                .set_numeric_owner(numeric_owner)
+                .set_list_format(format.into());
+            let entries = archiver.list(archive)?;
+            print!("{}", archiver.render_listing(&entries));
         }
-        Command::Append { archive, files } => {
+        Command::Append {
+            archive,
+            files,
+            format,
+            selection,
+            recompress,
+        } => {
+            let archive = Path::new(&archive);
+            let files = expand_inputs(&files)?;
+            archiver
+                .set_format(format.into())
+                .set_filter(selection.filter()?)
+                .set_compression_level(recompress.level);
+            for note in archiver.append(archive, files)? {
+                eprintln!("{}", note);
+            }
+        }
+        Command::Update {
+            archive,
+            files,
+            format,
+            selection,
+            recompress,
+        } => {
             let archive = Path::new(&archive);
-            archiver.append(archive, files)?;
+            let files = expand_inputs(&files)?;
+            archiver
+                .set_format(format.into())
+                .set_filter(selection.filter()?)
+                .set_compression_level(recompress.level);
+            for note in archiver.update(archive, files)? {
+                eprintln!("{}", note);
+            }
+        }
+        Command::Diff {
+            archive,
+            directory,
+            absolute_names,
+        } => {
+            archiver.set_absolute_names(absolute_names);
+            archiver.diff(Path::new(&archive), &directory)?;
+        }
+        Command::Concat {
+            archive,
+            sources,
+            recompress,
+        } => {
+            archiver.set_compression_level(recompress.level);
+            archiver.concat(Path::new(&archive), &sources)?;
+        }
+        Command::Delete {
+            archive,
+            members,
+            recompress,
+        } => {
+            archiver.set_compression_level(recompress.level);
+            archiver.delete(Path::new(&archive), &members)?;
         }
         Command::Extract {
             archive,
             output_dir,
+            members,
+            exclude,
+            absolute_names,
+            same_owner,
+            no_same_owner,
+            numeric_owner,
+            umask,
+            if_exists,
         } => {
             let archive = Path::new(&archive);
             let output_dir = Path::new(&output_dir);
-            archiver.extract(archive, output_dir)?;
+            archiver
+                .set_absolute_names(absolute_names)
+                .set_overwrite_policy(if_exists.into())
+                .set_numeric_owner(numeric_owner)
+                .set_member_filter(members, exclude);
+            if same_owner || no_same_owner {
+                archiver.set_same_owner(same_owner);
+            }
+            if let Some(umask) = umask {
+                archiver.set_umask(umask);
+            }
+            for note in archiver.extract(archive, output_dir)? {
+                eprintln!("{}", note);
+            }
         }
     }
     Ok(())
@@ -5,6 +5,4 @@ fn main() {
     }
 }
 
-
 // use perfermant read and write in case of large files
-// handle glob patterns *.txt, file?.txt, file[0-9].txt ..etc
\ No newline at end of file
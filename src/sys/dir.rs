@@ -0,0 +1,259 @@
+use std::{
+    ffi::{CString, OsStr},
+    fs::File,
+    io,
+    mem::MaybeUninit,
+    os::{
+        fd::{AsRawFd, FromRawFd, OwnedFd},
+        unix::ffi::OsStrExt,
+    },
+    path::Path,
+    time::Duration,
+};
+
+/// An open directory. Every operation takes a single path component relative
+/// to it and never follows a symlink in that component, so a chain of `Dir`s
+/// cannot be redirected outside the directory it started from.
+pub(crate) struct Dir {
+    fd: OwnedFd,
+}
+
+impl Dir {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let path = cstr(path.as_os_str())?;
+        let fd = cvt(unsafe {
+            libc::open(
+                path.as_ptr(),
+                libc::O_RDONLY | libc::O_DIRECTORY | libc::O_CLOEXEC,
+            )
+        })?;
+        Ok(Self::from_fd(fd))
+    }
+
+    pub fn try_clone(&self) -> io::Result<Self> {
+        Ok(Self {
+            fd: self.fd.try_clone()?,
+        })
+    }
+
+    /// Opens a subdirectory, failing if `name` is a symlink.
+    pub fn open_dir(&self, name: &OsStr) -> io::Result<Self> {
+        let name = cstr(name)?;
+        let fd = cvt(unsafe {
+            libc::openat(
+                self.fd.as_raw_fd(),
+                name.as_ptr(),
+                libc::O_RDONLY | libc::O_DIRECTORY | libc::O_NOFOLLOW | libc::O_CLOEXEC,
+            )
+        })?;
+        Ok(Self::from_fd(fd))
+    }
+
+    pub fn create_dir(&self, name: &OsStr, mode: u32) -> io::Result<()> {
+        let name = cstr(name)?;
+        cvt(unsafe { libc::mkdirat(self.fd.as_raw_fd(), name.as_ptr(), mode as libc::mode_t) })?;
+        Ok(())
+    }
+
+    /// Creates or truncates a regular file, failing if `name` is a symlink.
+    pub fn create_file(&self, name: &OsStr, mode: u32) -> io::Result<File> {
+        let name = cstr(name)?;
+        let fd = cvt(unsafe {
+            libc::openat(
+                self.fd.as_raw_fd(),
+                name.as_ptr(),
+                libc::O_WRONLY | libc::O_CREAT | libc::O_TRUNC | libc::O_NOFOLLOW | libc::O_CLOEXEC,
+                mode as libc::c_uint,
+            )
+        })?;
+        Ok(unsafe { File::from_raw_fd(fd) })
+    }
+
+    pub fn symlink(&self, target: &OsStr, name: &OsStr) -> io::Result<()> {
+        let target = cstr(target)?;
+        let name = cstr(name)?;
+        cvt(unsafe { libc::symlinkat(target.as_ptr(), self.fd.as_raw_fd(), name.as_ptr()) })?;
+        Ok(())
+    }
+
+    /// Creates a device node or FIFO. `kind` is one of the `S_IF*` file type
+    /// bits; `dev` is ignored for FIFOs.
+    pub fn mknod(
+        &self,
+        name: &OsStr,
+        kind: libc::mode_t,
+        mode: u32,
+        dev: libc::dev_t,
+    ) -> io::Result<()> {
+        let name = cstr(name)?;
+        cvt(unsafe {
+            libc::mknodat(
+                self.fd.as_raw_fd(),
+                name.as_ptr(),
+                kind | mode as libc::mode_t,
+                dev,
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Links `name` to `source` in `source_dir`. A symlink source is linked
+    /// itself rather than followed.
+    pub fn hard_link(&self, name: &OsStr, source_dir: &Dir, source: &OsStr) -> io::Result<()> {
+        let source = cstr(source)?;
+        let name = cstr(name)?;
+        cvt(unsafe {
+            libc::linkat(
+                source_dir.fd.as_raw_fd(),
+                source.as_ptr(),
+                self.fd.as_raw_fd(),
+                name.as_ptr(),
+                0,
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Returns the status of `name` without following symlinks, or `None` if
+    /// it does not exist.
+    pub fn lstat(&self, name: &OsStr) -> io::Result<Option<libc::stat>> {
+        let name = cstr(name)?;
+        let mut stat = MaybeUninit::<libc::stat>::uninit();
+        let ret = unsafe {
+            libc::fstatat(
+                self.fd.as_raw_fd(),
+                name.as_ptr(),
+                stat.as_mut_ptr(),
+                libc::AT_SYMLINK_NOFOLLOW,
+            )
+        };
+        match cvt(ret) {
+            Ok(_) => Ok(Some(unsafe { stat.assume_init() })),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn rename(&self, from: &OsStr, to: &OsStr) -> io::Result<()> {
+        let from = cstr(from)?;
+        let to = cstr(to)?;
+        cvt(unsafe {
+            libc::renameat(
+                self.fd.as_raw_fd(),
+                from.as_ptr(),
+                self.fd.as_raw_fd(),
+                to.as_ptr(),
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Changes the owner of `name` itself, not of a symlink's target.
+    pub fn chown(&self, name: &OsStr, uid: u32, gid: u32) -> io::Result<()> {
+        let name = cstr(name)?;
+        cvt(unsafe {
+            libc::fchownat(
+                self.fd.as_raw_fd(),
+                name.as_ptr(),
+                uid as libc::uid_t,
+                gid as libc::gid_t,
+                libc::AT_SYMLINK_NOFOLLOW,
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Sets the times of `name` itself, not of a symlink's target. A missing
+    /// access time is left unchanged.
+    pub fn set_times(
+        &self,
+        name: &OsStr,
+        atime: Option<Duration>,
+        mtime: Duration,
+    ) -> io::Result<()> {
+        let name = cstr(name)?;
+        let times = [timespec(atime), timespec(Some(mtime))];
+        cvt(unsafe {
+            libc::utimensat(
+                self.fd.as_raw_fd(),
+                name.as_ptr(),
+                times.as_ptr(),
+                libc::AT_SYMLINK_NOFOLLOW,
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Changes the permissions of `name`, which must not be a symlink.
+    pub fn chmod(&self, name: &OsStr, mode: u32) -> io::Result<()> {
+        let name = cstr(name)?;
+        cvt(unsafe {
+            libc::fchmodat(self.fd.as_raw_fd(), name.as_ptr(), mode as libc::mode_t, 0)
+        })?;
+        Ok(())
+    }
+
+    /// Changes the permissions of this directory.
+    pub fn set_mode(&self, mode: u32) -> io::Result<()> {
+        cvt(unsafe { libc::fchmod(self.fd.as_raw_fd(), mode as libc::mode_t) })?;
+        Ok(())
+    }
+
+    /// Removes `name`, which may be an empty directory.
+    pub fn remove(&self, name: &OsStr) -> io::Result<()> {
+        let flags = match self.lstat(name)? {
+            Some(stat) if is_dir(&stat) => libc::AT_REMOVEDIR,
+            Some(_) => 0,
+            None => return Ok(()),
+        };
+        let name = cstr(name)?;
+        cvt(unsafe { libc::unlinkat(self.fd.as_raw_fd(), name.as_ptr(), flags) })?;
+        Ok(())
+    }
+
+    fn from_fd(fd: libc::c_int) -> Self {
+        Self {
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+        }
+    }
+}
+
+pub(crate) fn is_dir(stat: &libc::stat) -> bool {
+    stat.st_mode & libc::S_IFMT == libc::S_IFDIR
+}
+
+/// Splits a device number into its major and minor parts.
+pub(crate) fn split_dev(dev: u64) -> (u64, u64) {
+    let dev = dev as libc::dev_t;
+    (libc::major(dev) as u64, libc::minor(dev) as u64)
+}
+
+pub(crate) fn make_dev(major: u64, minor: u64) -> libc::dev_t {
+    libc::makedev(major as libc::c_uint, minor as libc::c_uint)
+}
+
+fn timespec(time: Option<Duration>) -> libc::timespec {
+    match time {
+        Some(time) => libc::timespec {
+            tv_sec: time.as_secs() as libc::time_t,
+            tv_nsec: time.subsec_nanos() as libc::c_long,
+        },
+        None => libc::timespec {
+            tv_sec: 0,
+            tv_nsec: libc::UTIME_OMIT,
+        },
+    }
+}
+
+fn cstr(s: &OsStr) -> io::Result<CString> {
+    CString::new(s.as_bytes())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "path contains a NUL byte"))
+}
+
+fn cvt(ret: libc::c_int) -> io::Result<libc::c_int> {
+    if ret == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(ret)
+    }
+}
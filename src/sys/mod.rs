@@ -0,0 +1,7 @@
+mod dir;
+mod time;
+mod user;
+
+pub(crate) use dir::{Dir, is_dir, make_dev, split_dev};
+pub(crate) use time::format_local;
+pub(crate) use user::{OwnerCache, is_root, umask};
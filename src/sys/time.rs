@@ -0,0 +1,21 @@
+use std::mem::MaybeUninit;
+
+/// Formats seconds since the epoch as "YYYY-MM-DD HH:MM" in local time.
+pub(crate) fn format_local(secs: i64) -> String {
+    let time = secs as libc::time_t;
+    let mut tm = MaybeUninit::<libc::tm>::uninit();
+    let tm = unsafe {
+        if libc::localtime_r(&time, tm.as_mut_ptr()).is_null() {
+            return secs.to_string();
+        }
+        tm.assume_init()
+    };
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday,
+        tm.tm_hour,
+        tm.tm_min
+    )
+}
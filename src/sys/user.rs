@@ -0,0 +1,163 @@
+use std::{
+    collections::HashMap,
+    ffi::{CStr, CString},
+    fs,
+    mem::MaybeUninit,
+    ptr,
+};
+
+pub(crate) fn is_root() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
+
+/// Returns the process umask as reported by `/proc/self/status`. Calling
+/// `umask(2)` would have to change it, even if only briefly, which would race
+/// with files created by other threads. Falls back to the usual 022 when the
+/// field is not available.
+pub(crate) fn umask() -> u32 {
+    fs::read_to_string("/proc/self/status")
+        .ok()
+        .and_then(|status| {
+            status
+                .lines()
+                .find_map(|line| line.strip_prefix("Umask:"))
+                .and_then(|mask| u32::from_str_radix(mask.trim(), 8).ok())
+        })
+        .unwrap_or(0o022)
+}
+
+/// Remembers passwd and group lookups for the length of one run, since
+/// each can be slow (e.g. over LDAP) and most archives repeat a handful of
+/// owners. Failed lookups are remembered as well.
+#[derive(Default)]
+pub(crate) struct OwnerCache {
+    user_names: HashMap<u32, Option<String>>,
+    group_names: HashMap<u32, Option<String>>,
+    user_ids: HashMap<String, Option<u32>>,
+    group_ids: HashMap<String, Option<u32>>,
+}
+
+impl OwnerCache {
+    pub(crate) fn user_name(&mut self, uid: u32) -> Option<String> {
+        self.user_names
+            .entry(uid)
+            .or_insert_with(|| user_name(uid))
+            .clone()
+    }
+
+    pub(crate) fn group_name(&mut self, gid: u32) -> Option<String> {
+        self.group_names
+            .entry(gid)
+            .or_insert_with(|| group_name(gid))
+            .clone()
+    }
+
+    pub(crate) fn user_id(&mut self, name: &str) -> Option<u32> {
+        if let Some(&uid) = self.user_ids.get(name) {
+            return uid;
+        }
+        let uid = user_id(name);
+        self.user_ids.insert(name.to_string(), uid);
+        uid
+    }
+
+    pub(crate) fn group_id(&mut self, name: &str) -> Option<u32> {
+        if let Some(&gid) = self.group_ids.get(name) {
+            return gid;
+        }
+        let gid = group_id(name);
+        self.group_ids.insert(name.to_string(), gid);
+        gid
+    }
+}
+
+/// Looks up the login name of `uid` in the passwd database.
+fn user_name(uid: u32) -> Option<String> {
+    let mut passwd = MaybeUninit::<libc::passwd>::uninit();
+    lookup(
+        |buf, result| unsafe {
+            libc::getpwuid_r(
+                uid,
+                passwd.as_mut_ptr(),
+                buf.as_mut_ptr(),
+                buf.len(),
+                result,
+            )
+        },
+        |passwd| name_of(passwd.pw_name),
+    )
+}
+
+/// Looks up the name of `gid` in the group database.
+fn group_name(gid: u32) -> Option<String> {
+    let mut group = MaybeUninit::<libc::group>::uninit();
+    lookup(
+        |buf, result| unsafe {
+            libc::getgrgid_r(gid, group.as_mut_ptr(), buf.as_mut_ptr(), buf.len(), result)
+        },
+        |group| name_of(group.gr_name),
+    )
+}
+
+fn user_id(name: &str) -> Option<u32> {
+    let name = CString::new(name).ok()?;
+    let mut passwd = MaybeUninit::<libc::passwd>::uninit();
+    lookup(
+        |buf, result| unsafe {
+            libc::getpwnam_r(
+                name.as_ptr(),
+                passwd.as_mut_ptr(),
+                buf.as_mut_ptr(),
+                buf.len(),
+                result,
+            )
+        },
+        |passwd| Some(passwd.pw_uid),
+    )
+}
+
+fn group_id(name: &str) -> Option<u32> {
+    let name = CString::new(name).ok()?;
+    let mut group = MaybeUninit::<libc::group>::uninit();
+    lookup(
+        |buf, result| unsafe {
+            libc::getgrnam_r(
+                name.as_ptr(),
+                group.as_mut_ptr(),
+                buf.as_mut_ptr(),
+                buf.len(),
+                result,
+            )
+        },
+        |group| Some(group.gr_gid),
+    )
+}
+
+/// Runs a reentrant passwd/group lookup, growing the string buffer until it
+/// fits, and reads what is needed from the record while the buffer its
+/// strings point into is still alive.
+fn lookup<T, U>(
+    mut call: impl FnMut(&mut [libc::c_char], *mut *mut T) -> libc::c_int,
+    read: impl FnOnce(&T) -> Option<U>,
+) -> Option<U> {
+    let mut buf = vec![0 as libc::c_char; 1024];
+    loop {
+        let mut result = ptr::null_mut();
+        match call(&mut buf, &mut result) {
+            0 if result.is_null() => return None,
+            0 => return read(unsafe { &*result }),
+            libc::ERANGE if buf.len() < 1 << 20 => buf.resize(buf.len() * 2, 0),
+            _ => return None,
+        }
+    }
+}
+
+fn name_of(name: *const libc::c_char) -> Option<String> {
+    if name.is_null() {
+        return None;
+    }
+    unsafe { CStr::from_ptr(name) }
+        .to_str()
+        .ok()
+        .map(String::from)
+}
@@ -1,5 +1,7 @@
 use std::path::Path;
 
+use crate::archive::Codec;
+
 mod error;
 mod extension;
 mod structure;
@@ -13,7 +15,22 @@ pub struct ArchiveValidator {
     ext_validator: ExtensionValidator,
 }
 
+impl Default for ArchiveValidator {
+    fn default() -> Self {
+        Self::new(Self::default_extensions())
+    }
+}
+
 impl ArchiveValidator {
+    /// Plain tar extensions plus those of every recognized compression codec.
+    pub fn default_extensions() -> Vec<String> {
+        ["tar", "rustar"]
+            .into_iter()
+            .chain(Codec::all_extensions())
+            .map(String::from)
+            .collect()
+    }
+
     pub fn new(allowed_extensions: Vec<String>) -> Self {
         Self {
             ext_validator: ExtensionValidator::new(allowed_extensions),
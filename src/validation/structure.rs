@@ -1,7 +1,9 @@
-use crate::header::{HeaderParser, HeaderValidator, constants::*};
+use crate::{
+    archive::Codec,
+    header::{HeaderParser, HeaderValidator, constants::*},
+};
 use std::{
-    fs::File,
-    io::{BufReader, Read, Seek, SeekFrom},
+    io::{self, Read},
     path::Path,
 };
 
@@ -11,21 +13,17 @@ pub struct ArchiveStructureValidator;
 
 impl ArchiveStructureValidator {
     pub fn validate(archive_path: &Path) -> Result<(), ValidationError> {
-        let file = File::open(archive_path)?;
-        let mut reader = BufReader::new(file);
-        let file_size = reader.get_ref().metadata()?.len();
-
-        if file_size < (BLOCK_SIZE * END_MARKER_BLOCKS) as u64 {
-            return Err(ValidationError::InvalidStructure(format!(
-                "Archive too small ({} bytes), must be at least {} bytes",
-                file_size,
-                BLOCK_SIZE * END_MARKER_BLOCKS
-            )));
-        }
+        let (_, mut reader) = Codec::open(archive_path)?;
+        let mut archive_size = 0u64;
+        let mut trailing_zero_blocks = 0;
+        // Sizes from pax records override the size field of the member
+        // header that follows, or of every later one for global records.
+        let mut local_size = None;
+        let mut global_size = None;
 
         loop {
             let mut header = [0u8; BLOCK_SIZE];
-            let bytes_read = reader.read(&mut header)?;
+            let bytes_read = Self::read_block(&mut reader, &mut header)?;
             if bytes_read == 0 {
                 break;
             }
@@ -35,23 +33,69 @@ impl ArchiveStructureValidator {
                     "Incomplete header block (not 512 bytes)".to_string(),
                 ));
             }
+            archive_size += BLOCK_SIZE as u64;
             if header.iter().all(|&b| b == 0) {
+                trailing_zero_blocks += 1;
                 continue;
             }
+            trailing_zero_blocks = 0;
             HeaderValidator::validate(&header)
                 .map_err(|e| ValidationError::InvalidStructure(e.to_string()))?;
-            let size = HeaderParser::parse_size(&header)
-                .map_err(|e| ValidationError::InvalidStructure(e.to_string()))?
-                as usize;
-            let padding = (BLOCK_SIZE - (size % BLOCK_SIZE)) % BLOCK_SIZE;
-            reader.seek_relative((size + padding) as i64)?;
+            let mut size = HeaderParser::parse_size(&header)
+                .map_err(|e| ValidationError::InvalidStructure(e.to_string()))?;
+            let typeflag = header[TYPEFLAG_FIELD];
+            let is_pax = matches!(typeflag, TYPEFLAG_PAX_HEADER | TYPEFLAG_PAX_GLOBAL);
+            let is_extension =
+                is_pax || matches!(typeflag, TYPEFLAG_GNU_LONGNAME | TYPEFLAG_GNU_LONGLINK);
+            if !is_extension {
+                size = local_size.take().or(global_size).unwrap_or(size);
+            }
+            if is_extension && size > MAX_EXTENSION_SIZE {
+                return Err(ValidationError::InvalidStructure(format!(
+                    "Extension header data of {} bytes exceeds the {} byte limit",
+                    size, MAX_EXTENSION_SIZE
+                )));
+            }
+            let padding = (BLOCK_SIZE as u64 - (size % BLOCK_SIZE as u64)) % BLOCK_SIZE as u64;
+            let total = size.checked_add(padding).ok_or_else(|| {
+                ValidationError::InvalidStructure(format!("Member size {} out of range", size))
+            })?;
+            let mut data = (&mut reader).take(total);
+            let skipped = if is_pax {
+                let mut records = Vec::new();
+                let read = data.read_to_end(&mut records)? as u64;
+                if read == total {
+                    records.truncate(size as usize);
+                    let pax_size = HeaderParser::parse_pax(&records)
+                        .and_then(|records| HeaderParser::pax_size(&records))
+                        .map_err(|e| ValidationError::InvalidStructure(e.to_string()))?;
+                    if typeflag == TYPEFLAG_PAX_HEADER {
+                        local_size = pax_size;
+                    } else if pax_size.is_some() {
+                        global_size = pax_size;
+                    }
+                }
+                read
+            } else {
+                io::copy(&mut data, &mut io::sink())?
+            };
+            if skipped != total {
+                return Err(ValidationError::InvalidStructure(
+                    "Archive truncated inside member data".to_string(),
+                ));
+            }
+            archive_size += skipped;
         }
 
-        let end_pos = (BLOCK_SIZE * END_MARKER_BLOCKS) as i64;
-        reader.seek(SeekFrom::End(-end_pos))?;
-        let mut end = [0u8; BLOCK_SIZE * END_MARKER_BLOCKS];
-        reader.read_exact(&mut end)?;
-        if end.iter().any(|&b| b != 0) {
+        if archive_size < (BLOCK_SIZE * END_MARKER_BLOCKS) as u64 {
+            return Err(ValidationError::InvalidStructure(format!(
+                "Archive too small ({} bytes), must be at least {} bytes",
+                archive_size,
+                BLOCK_SIZE * END_MARKER_BLOCKS
+            )));
+        }
+
+        if trailing_zero_blocks < END_MARKER_BLOCKS {
             return Err(ValidationError::InvalidStructure(
                 "Archive missing proper zero padding at end".to_string(),
             ));
@@ -59,4 +103,17 @@ impl ArchiveStructureValidator {
 
         Ok(())
     }
+
+    /// Fills `block` as far as the stream allows; decompressors may return
+    /// short reads in the middle of a block.
+    fn read_block(reader: &mut impl Read, block: &mut [u8]) -> io::Result<usize> {
+        let mut filled = 0;
+        while filled < block.len() {
+            match reader.read(&mut block[filled..])? {
+                0 => break,
+                n => filled += n,
+            }
+        }
+        Ok(filled)
+    }
 }
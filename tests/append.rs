@@ -0,0 +1,156 @@
+use rustar::{Archive, Archiver, Builder, EntryType, ParsedHeader};
+use std::{fs, io::Cursor, path::Path};
+use tempfile::TempDir;
+
+fn names(archiver: &Archiver, archive: &Path) -> Vec<String> {
+    archiver
+        .list(archive)
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.name)
+        .collect()
+}
+
+/// Checks that the archive ends right after the last member's data with
+/// exactly two zero blocks.
+fn assert_terminated(archive: &Path) {
+    let data = fs::read(archive).unwrap();
+    assert_eq!(data.len() % 512, 0);
+    assert!(data[data.len() - 1024..].iter().all(|&b| b == 0));
+    assert!(
+        data[data.len() - 1536..data.len() - 1024]
+            .iter()
+            .any(|&b| b != 0)
+    );
+}
+
+#[test]
+fn create_append_list_round_trip() {
+    let dir = TempDir::new().unwrap();
+    let (a, b, c) = (
+        dir.path().join("a"),
+        dir.path().join("b"),
+        dir.path().join("c"),
+    );
+    fs::write(&a, "first").unwrap();
+    fs::write(&b, "second").unwrap();
+    fs::write(&c, "third").unwrap();
+    let archive = dir.path().join("out.tar");
+
+    let archiver = Archiver::default();
+    archiver.create(&archive, vec![&a]).unwrap();
+    archiver.append(&archive, vec![&b]).unwrap();
+    archiver.append(&archive, vec![&c]).unwrap();
+
+    assert_eq!(names(&archiver, &archive), ["a", "b", "c"]);
+    assert_terminated(&archive);
+
+    let mut reader = Archive::new(fs::File::open(&archive).unwrap());
+    let mut contents = Vec::new();
+    for entry in reader.entries() {
+        let mut entry = entry.unwrap();
+        let mut data = String::new();
+        std::io::Read::read_to_string(&mut entry, &mut data).unwrap();
+        contents.push(data);
+    }
+    assert_eq!(contents, ["first", "second", "third"]);
+}
+
+#[test]
+fn append_to_archive_padded_to_a_full_record() {
+    let dir = TempDir::new().unwrap();
+    let archive = dir.path().join("padded.tar");
+    let mut builder = Builder::new(Vec::new());
+    let header = ParsedHeader::new("old", EntryType::Regular, 3);
+    builder.append_data(&header, &b"old"[..]).unwrap();
+    let mut data = builder.into_inner().unwrap();
+    // GNU tar pads archives to a 10240-byte record.
+    data.resize(10240, 0);
+    fs::write(&archive, data).unwrap();
+
+    let new = dir.path().join("new");
+    fs::write(&new, "new").unwrap();
+    let archiver = Archiver::default();
+    archiver.append(&archive, vec![&new]).unwrap();
+
+    assert_eq!(names(&archiver, &archive), ["old", "new"]);
+    // The record padding is gone: "new" starts right after "old", whose
+    // header and data take two blocks, and one data block precedes the
+    // end marker.
+    let data = fs::read(&archive).unwrap();
+    let mut reader = Archive::new(Cursor::new(&data));
+    let offsets: Vec<_> = reader
+        .entries()
+        .map(|entry| {
+            let entry = entry.unwrap();
+            (entry.header_offset(), entry.data_offset())
+        })
+        .collect();
+    assert_eq!(offsets[1].0, 2 * 512);
+    assert_eq!(data.len() as u64, offsets[1].1 + 512 + 1024);
+    assert_terminated(&archive);
+}
+
+#[test]
+fn append_to_compressed_archive() {
+    let dir = TempDir::new().unwrap();
+    let (a, b) = (dir.path().join("a"), dir.path().join("b"));
+    fs::write(&a, "a").unwrap();
+    fs::write(&b, "b").unwrap();
+    let archive = dir.path().join("out.tar.gz");
+
+    let archiver = Archiver::default();
+    archiver.create(&archive, vec![&a]).unwrap();
+    archiver.append(&archive, vec![&b]).unwrap();
+
+    assert_eq!(names(&archiver, &archive), ["a", "b"]);
+}
+
+#[test]
+#[cfg(feature = "bzip2")]
+fn append_to_compressed_archive_keeps_level_and_leaves_other_files_alone() {
+    let dir = TempDir::new().unwrap();
+    let (a, b) = (dir.path().join("a"), dir.path().join("b"));
+    fs::write(&a, "a").unwrap();
+    fs::write(&b, "b").unwrap();
+    let archive = dir.path().join("out.tar.bz2");
+    let stray = dir.path().join("out.tar.bz2.tmp");
+    fs::write(&stray, "not ours").unwrap();
+
+    let mut archiver = Archiver::default();
+    archiver.set_compression_level(Some(3));
+    archiver.create(&archive, vec![&a]).unwrap();
+    Archiver::default().append(&archive, vec![&b]).unwrap();
+
+    assert_eq!(&fs::read(&archive).unwrap()[..4], b"BZh3");
+    assert_eq!(names(&archiver, &archive), ["a", "b"]);
+    assert_eq!(fs::read(&stray).unwrap(), b"not ours");
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 4);
+
+    archiver.set_compression_level(Some(7));
+    archiver.append(&archive, vec![&a]).unwrap();
+    assert_eq!(&fs::read(&archive).unwrap()[..4], b"BZh7");
+}
+
+#[test]
+fn parent_directory_input_is_rooted_at_its_contents() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir(dir.path().join("sub")).unwrap();
+    fs::write(dir.path().join("f"), "f").unwrap();
+    let mut builder = Builder::new(Vec::new());
+    builder.append_path(dir.path().join("sub/..")).unwrap();
+    let data = builder.into_inner().unwrap();
+
+    let mut archive = Archive::new(Cursor::new(&data));
+    let names: Vec<_> = archive
+        .entries()
+        .map(|entry| entry.unwrap().header().name.clone())
+        .collect();
+    assert_eq!(names, ["./", "./f", "./sub/"]);
+
+    // rustar must be able to extract what it wrote.
+    let out = dir.path().join("out");
+    Archive::new(Cursor::new(&data)).unpack(&out).unwrap();
+    assert_eq!(fs::read(out.join("f")).unwrap(), b"f");
+    assert!(out.join("sub").is_dir());
+}
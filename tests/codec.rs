@@ -0,0 +1,42 @@
+use rustar::{Archiver, archive::Codec};
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn plain_archive_named_like_bzip2_magic_is_not_decompressed() {
+    let dir = TempDir::new().unwrap();
+    for name in ["BZh91AY.txt", "BZh91AY&SY.txt"] {
+        let file = dir.path().join(name);
+        fs::write(&file, "x").unwrap();
+        let archive = dir.path().join("a.tar");
+        let archiver = Archiver::default();
+        archiver.create(&archive, vec![&file]).unwrap();
+
+        let data = fs::read(&archive).unwrap();
+        assert_eq!(Codec::sniff(&data), Codec::None);
+        let names: Vec<_> = archiver
+            .list(&archive)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.name)
+            .collect();
+        assert_eq!(names, [name]);
+    }
+}
+
+#[test]
+fn bzip2_needs_block_magic() {
+    assert_eq!(Codec::sniff(b"BZh91AY&SY\x01\x02"), Codec::Bzip2);
+    assert_eq!(Codec::sniff(b"BZh9\x17\x72\x45\x38\x50\x90"), Codec::Bzip2);
+    assert_eq!(Codec::sniff(b"BZh91AY.txt"), Codec::None);
+    assert_eq!(Codec::sniff(b"BZh"), Codec::None);
+    assert_eq!(Codec::sniff(&[0x1f, 0x8b, 8]), Codec::Gzip);
+}
+
+#[test]
+#[cfg(feature = "bzip2")]
+fn empty_bzip2_stream_is_recognized() {
+    let encoder = Codec::Bzip2.encoder(Vec::new(), None).unwrap();
+    let data = encoder.finish().unwrap();
+    assert_eq!(Codec::sniff(&data), Codec::Bzip2);
+}
@@ -0,0 +1,81 @@
+use rustar::{Archive, Archiver, ArchiverError, Builder, EntryType, ParsedHeader};
+use std::{fs, io::Cursor, path::Path};
+use tempfile::TempDir;
+
+/// Writes one-block members named `names`, padded to `record` bytes.
+fn write_archive(path: &Path, names: &[&str], record: usize) {
+    let mut builder = Builder::new(Vec::new());
+    for name in names {
+        let header = ParsedHeader::new(name, EntryType::Regular, 1);
+        builder.append_data(&header, &b"x"[..]).unwrap();
+    }
+    let mut data = builder.into_inner().unwrap();
+    data.resize(data.len().div_ceil(record) * record, 0);
+    fs::write(path, data).unwrap();
+}
+
+/// Member names with the offset of each header.
+fn layout(path: &Path) -> Vec<(String, u64)> {
+    let data = fs::read(path).unwrap();
+    let mut archive = Archive::new(Cursor::new(data));
+    archive
+        .entries()
+        .map(|entry| {
+            let entry = entry.unwrap();
+            (entry.header().name.clone(), entry.header_offset())
+        })
+        .collect()
+}
+
+#[test]
+fn strips_intermediate_end_markers() {
+    let dir = TempDir::new().unwrap();
+    let (a, b, c) = (
+        dir.path().join("a.tar"),
+        dir.path().join("b.tar"),
+        dir.path().join("c.tar"),
+    );
+    write_archive(&a, &["a"], 10240);
+    write_archive(&b, &["b1", "b2"], 512);
+    write_archive(&c, &["c"], 10240);
+
+    Archiver::default().concat(&a, &[&b, &c]).unwrap();
+
+    let expected: Vec<(String, u64)> = ["a", "b1", "b2", "c"]
+        .iter()
+        .zip((0..).step_by(1024))
+        .map(|(name, offset)| (name.to_string(), offset))
+        .collect();
+    assert_eq!(layout(&a), expected);
+    let data = fs::read(&a).unwrap();
+    assert_eq!(data.len(), 4 * 1024 + 1024);
+    assert!(data[4 * 1024..].iter().all(|&b| b == 0));
+}
+
+#[test]
+fn accepts_compressed_sources() {
+    let dir = TempDir::new().unwrap();
+    let file = dir.path().join("f");
+    fs::write(&file, "f").unwrap();
+    let (a, b) = (dir.path().join("a.tar"), dir.path().join("b.tar.gz"));
+    write_archive(&a, &["a"], 512);
+    let archiver = Archiver::default();
+    archiver.create(&b, vec![&file]).unwrap();
+
+    archiver.concat(&a, &[&b]).unwrap();
+    let names: Vec<_> = layout(&a).into_iter().map(|(name, _)| name).collect();
+    assert_eq!(names, ["a", "f"]);
+}
+
+#[test]
+fn refuses_to_concatenate_archive_onto_itself() {
+    let dir = TempDir::new().unwrap();
+    let a = dir.path().join("a.tar");
+    write_archive(&a, &["a"], 512);
+    let before = fs::read(&a).unwrap();
+
+    let same = dir.path().join(".").join("a.tar");
+    let err = Archiver::default().concat(&a, &[&same]).unwrap_err();
+    assert!(matches!(err, ArchiverError::UnsupportedFeature(_)), "{err}");
+    assert_eq!(fs::read(&a).unwrap(), before);
+}
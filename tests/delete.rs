@@ -0,0 +1,142 @@
+use rustar::{
+    Archive, Archiver, ArchiverError, Builder, EntryType, HeaderFormat, ParsedHeader,
+    archive::Codec, glob::Pattern,
+};
+use std::{fs, io::Read, path::Path};
+use tempfile::TempDir;
+
+/// Writes members named after `names`, each holding its own name, padded
+/// to `record` bytes.
+fn write_archive(path: &Path, names: &[&str], format: HeaderFormat, record: usize) {
+    let mut builder = Builder::new(Vec::new());
+    builder.set_format(format);
+    for name in names {
+        let header = ParsedHeader::new(name, EntryType::Regular, name.len() as u64);
+        builder.append_data(&header, name.as_bytes()).unwrap();
+    }
+    let mut data = builder.into_inner().unwrap();
+    data.resize(data.len().div_ceil(record) * record, 0);
+    fs::write(path, data).unwrap();
+}
+
+/// Reads back every member as (name, data).
+fn members(path: &Path) -> Vec<(String, String)> {
+    let (_, reader) = Codec::open(path).unwrap();
+    let mut archive = Archive::new(reader);
+    archive
+        .entries()
+        .map(|entry| {
+            let mut entry = entry.unwrap();
+            let mut data = String::new();
+            entry.read_to_string(&mut data).unwrap();
+            (entry.header().name.clone(), data)
+        })
+        .collect()
+}
+
+fn delete(path: &Path, patterns: &[&str]) -> Result<(), ArchiverError> {
+    let patterns: Vec<Pattern> = patterns.iter().map(|p| Pattern::new(p).unwrap()).collect();
+    Archiver::default().delete(path, &patterns)
+}
+
+fn names_and_data(names: &[&str]) -> Vec<(String, String)> {
+    names
+        .iter()
+        .map(|n| (n.to_string(), n.to_string()))
+        .collect()
+}
+
+#[test]
+fn deletes_first_middle_and_last_members() {
+    let dir = TempDir::new().unwrap();
+    for (deleted, kept) in [
+        ("a", ["b", "c", "d"]),
+        ("b", ["a", "c", "d"]),
+        ("d", ["a", "b", "c"]),
+    ] {
+        let path = dir.path().join("a.tar");
+        write_archive(&path, &["a", "b", "c", "d"], HeaderFormat::Pax, 512);
+        delete(&path, &[deleted]).unwrap();
+        assert_eq!(members(&path), names_and_data(&kept), "deleting {deleted}");
+        // Kept members plus the end marker, nothing after it.
+        assert_eq!(fs::metadata(&path).unwrap().len(), 3 * 1024 + 1024);
+    }
+}
+
+#[test]
+fn deletes_members_with_extension_headers() {
+    let dir = TempDir::new().unwrap();
+    let long = "l".repeat(150);
+    let other = format!("{}/x", "d".repeat(120));
+    for format in [HeaderFormat::Pax, HeaderFormat::Gnu] {
+        let path = dir.path().join("a.tar");
+        write_archive(&path, &["a", &long, "b", &other], format, 512);
+        delete(&path, &[&long]).unwrap();
+        assert_eq!(members(&path), names_and_data(&["a", "b", &other]));
+        delete(&path, &["a", &other]).unwrap();
+        assert_eq!(members(&path), names_and_data(&["b"]), "{format:?}");
+    }
+}
+
+#[test]
+fn deletes_from_record_padded_archive() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("a.tar");
+    write_archive(&path, &["a", "b"], HeaderFormat::Pax, 10240);
+    assert_eq!(fs::metadata(&path).unwrap().len(), 10240);
+
+    delete(&path, &["a"]).unwrap();
+    assert_eq!(members(&path), names_and_data(&["b"]));
+    let data = fs::read(&path).unwrap();
+    assert_eq!(data.len(), 1024 + 1024);
+    assert!(data[1024..].iter().all(|&b| b == 0));
+}
+
+#[test]
+fn pattern_matching_nothing_leaves_file_untouched() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("a.tar");
+    write_archive(&path, &["a", "b"], HeaderFormat::Pax, 10240);
+    let before = fs::read(&path).unwrap();
+
+    let err = delete(&path, &["missing"]).unwrap_err();
+    assert!(matches!(err, ArchiverError::MembersNotFound(ref m) if m == &["missing"]));
+    assert_eq!(fs::read(&path).unwrap(), before);
+}
+
+#[test]
+fn rewrites_compressed_archive_with_its_level() {
+    let dir = TempDir::new().unwrap();
+    let files: Vec<_> = ["a", "b", "c"]
+        .iter()
+        .map(|name| {
+            let file = dir.path().join(name);
+            fs::write(&file, name).unwrap();
+            file
+        })
+        .collect();
+    let path = dir.path().join("out.tar.gz");
+    let stray = dir.path().join("out.tar.gz.tmp");
+    fs::write(&stray, "not ours").unwrap();
+
+    let mut archiver = Archiver::default();
+    archiver.set_compression_level(Some(9));
+    archiver.create(&path, files).unwrap();
+    delete(&path, &["a"]).unwrap();
+
+    let data = fs::read(&path).unwrap();
+    assert_eq!(&data[..2], &[0x1f, 0x8b]);
+    // gzip records the best level as 2 in its extra flags.
+    assert_eq!(data[8], 2);
+    assert_eq!(members(&path), names_and_data(&["b", "c"]));
+    assert_eq!(fs::read(&stray).unwrap(), b"not ours");
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 5);
+
+    // An explicit level wins over the recorded one; 4 marks the fastest.
+    archiver.set_compression_level(Some(1));
+    archiver
+        .delete(&path, &[Pattern::new("b").unwrap()])
+        .unwrap();
+    assert_eq!(fs::read(&path).unwrap()[8], 4);
+    assert_eq!(members(&path), names_and_data(&["c"]));
+}
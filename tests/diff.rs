@@ -0,0 +1,43 @@
+use rustar::{Archiver, ArchiverError, Builder, EntryType, ParsedHeader};
+use std::{fs, fs::File, path::Path};
+use tempfile::TempDir;
+
+fn write_archive(path: &Path, name: &str, data: &[u8]) {
+    let mut builder = Builder::new(File::create(path).unwrap());
+    let header = ParsedHeader::new(name, EntryType::Regular, data.len() as u64);
+    builder.append_data(&header, data).unwrap();
+    builder.into_inner().unwrap();
+}
+
+#[test]
+fn refuses_parent_directory_components() {
+    let dir = TempDir::new().unwrap();
+    let out = dir.path().join("out");
+    fs::create_dir(&out).unwrap();
+    fs::write(dir.path().join("secret"), "outside").unwrap();
+    let archive = dir.path().join("a.tar");
+    write_archive(&archive, "../secret", b"outside");
+
+    let err = Archiver::default().diff(&archive, &out).unwrap_err();
+    let ArchiverError::Differences(differences) = err else {
+        panic!("expected differences, got {err}");
+    };
+    assert_eq!(differences, ["../secret: contains '..', not compared"]);
+}
+
+#[test]
+fn follows_parent_directory_components_with_absolute_names() {
+    let dir = TempDir::new().unwrap();
+    let out = dir.path().join("out");
+    fs::create_dir(&out).unwrap();
+    let archive = dir.path().join("a.tar");
+    write_archive(&archive, "../missing", b"");
+
+    let mut archiver = Archiver::default();
+    archiver.set_absolute_names(true);
+    let err = archiver.diff(&archive, &out).unwrap_err();
+    let ArchiverError::Differences(differences) = err else {
+        panic!("expected differences, got {err}");
+    };
+    assert_eq!(differences, ["../missing: missing on disk"]);
+}
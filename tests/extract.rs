@@ -0,0 +1,278 @@
+use rustar::{
+    Archive, ArchiverError, Builder, EntryType, ParsedHeader,
+    archive::{ArchiveExtractor, OverwritePolicy},
+};
+use std::{
+    fs,
+    io::Cursor,
+    os::unix::fs::{FileTypeExt, MetadataExt},
+    path::Path,
+};
+use tempfile::TempDir;
+
+/// Builds an in-memory archive from (name, type, linkname, data) members.
+fn archive(members: &[(&str, EntryType, &str, &[u8])]) -> Vec<u8> {
+    let mut builder = Builder::new(Vec::new());
+    for &(name, entry_type, linkname, data) in members {
+        let mut header = ParsedHeader::new(name, entry_type, data.len() as u64);
+        header.linkname = linkname.to_string();
+        builder.append_data(&header, data).unwrap();
+    }
+    builder.into_inner().unwrap()
+}
+
+fn unpack(data: Vec<u8>, output_dir: &Path) -> Result<Vec<String>, ArchiverError> {
+    Archive::new(Cursor::new(data)).unpack(output_dir)
+}
+
+#[test]
+fn refuses_parent_directory_components() {
+    let dir = TempDir::new().unwrap();
+    let out = dir.path().join("out");
+    let data = archive(&[("../x", EntryType::Regular, "", b"escaped")]);
+
+    let err = unpack(data, &out).unwrap_err();
+    assert!(matches!(err, ArchiverError::RejectedMembers(ref m) if m[0].contains("'..'")));
+    assert!(!dir.path().join("x").exists());
+}
+
+#[test]
+fn strips_leading_slash() {
+    let dir = TempDir::new().unwrap();
+    let out = dir.path().join("out");
+    let target = dir.path().join("abs");
+    let name = target.to_str().unwrap().to_string();
+    let data = archive(&[(&name, EntryType::Regular, "", b"contained")]);
+
+    unpack(data, &out).unwrap();
+    assert!(!target.exists());
+    let inside = out.join(name.trim_start_matches('/'));
+    assert_eq!(fs::read(inside).unwrap(), b"contained");
+}
+
+#[test]
+fn refuses_to_write_through_symlink() {
+    let dir = TempDir::new().unwrap();
+    let out = dir.path().join("out");
+    let outside = dir.path().join("outside");
+    fs::create_dir(&outside).unwrap();
+    let data = archive(&[
+        ("link", EntryType::Symlink, outside.to_str().unwrap(), b""),
+        ("link/victim", EntryType::Regular, "", b"escaped"),
+    ]);
+
+    let err = unpack(data, &out).unwrap_err();
+    assert!(matches!(err, ArchiverError::RejectedMembers(ref m) if m[0].contains("symbolic link")));
+    assert!(!outside.join("victim").exists());
+    assert!(fs::symlink_metadata(out.join("link")).unwrap().is_symlink());
+}
+
+#[test]
+fn refuses_to_replace_planted_symlink() {
+    let dir = TempDir::new().unwrap();
+    let out = dir.path().join("out");
+    let victim = dir.path().join("victim");
+    fs::write(&victim, "original").unwrap();
+    let data = archive(&[
+        ("evil", EntryType::Symlink, victim.to_str().unwrap(), b""),
+        ("evil", EntryType::Regular, "", b"overwritten"),
+    ]);
+
+    assert!(unpack(data, &out).is_err());
+    assert_eq!(fs::read(&victim).unwrap(), b"original");
+}
+
+#[test]
+fn keeps_notes_when_members_are_rejected() {
+    let dir = TempDir::new().unwrap();
+    let out = dir.path().join("out");
+    fs::create_dir(&out).unwrap();
+    fs::write(out.join("ok.txt"), "old").unwrap();
+    let data = archive(&[
+        ("ok.txt", EntryType::Regular, "", b"new"),
+        ("../evil", EntryType::Regular, "", b""),
+    ]);
+
+    let mut extractor = ArchiveExtractor::default();
+    extractor.set_overwrite_policy(OverwritePolicy::Backup);
+    let err = extractor
+        .extract_from(&mut Archive::new(Cursor::new(data)), &out)
+        .unwrap_err();
+    let ArchiverError::WithNotes(notes, err) = err else {
+        panic!("expected notes, got {err}");
+    };
+    assert_eq!(notes, ["ok.txt: existing file renamed to ok.txt.~1~"]);
+    assert!(matches!(*err, ArchiverError::RejectedMembers(_)));
+    assert_eq!(fs::read(out.join("ok.txt.~1~")).unwrap(), b"old");
+}
+
+#[test]
+fn refuses_block_without_valid_header() {
+    let dir = TempDir::new().unwrap();
+    let out = dir.path().join("out");
+    let mut data = vec![b' '; 512];
+    data.extend([0u8; 1024]);
+
+    let err = unpack(data, &out).unwrap_err();
+    assert!(matches!(err, ArchiverError::HeaderError(_)), "{err}");
+    assert!(fs::read_dir(&out).map_or(true, |mut d| d.next().is_none()));
+}
+
+#[test]
+fn rejects_hard_link_to_missing_target_and_keeps_going() {
+    let dir = TempDir::new().unwrap();
+    let out = dir.path().join("out");
+    let data = archive(&[
+        ("h", EntryType::HardLink, "missing", b""),
+        ("z", EntryType::Regular, "", b"after"),
+    ]);
+
+    let err = unpack(data, &out).unwrap_err();
+    assert!(
+        matches!(err, ArchiverError::RejectedMembers(ref m) if m == &["h: link target 'missing' does not exist"]),
+        "{err}"
+    );
+    assert_eq!(fs::read(out.join("z")).unwrap(), b"after");
+}
+
+fn archive_of(members: &[(ParsedHeader, &[u8])]) -> Vec<u8> {
+    let mut builder = Builder::new(Vec::new());
+    for (header, data) in members {
+        builder.append_data(header, *data).unwrap();
+    }
+    builder.into_inner().unwrap()
+}
+
+fn process_umask() -> u32 {
+    let status = fs::read_to_string("/proc/self/status").unwrap();
+    let line = status.lines().find(|l| l.starts_with("Umask:")).unwrap();
+    u32::from_str_radix(line["Umask:".len()..].trim(), 8).unwrap()
+}
+
+#[test]
+fn restores_mode_and_mtime_under_process_umask() {
+    let dir = TempDir::new().unwrap();
+    let out = dir.path().join("out");
+    let mut file = ParsedHeader::new("d/f", EntryType::Regular, 1);
+    file.mode = 0o777;
+    file.mtime = 1_600_000_000;
+    file.mtime_nsec = 500_000_000;
+    let mut directory = ParsedHeader::new("d/", EntryType::Directory, 0);
+    directory.mode = 0o777;
+    directory.mtime = 1_500_000_000;
+    let data = archive_of(&[(directory, b""), (file, b"x")]);
+
+    unpack(data, &out).unwrap();
+    let umask = process_umask();
+    let file = fs::metadata(out.join("d/f")).unwrap();
+    assert_eq!(file.mode() & 0o7777, 0o777 & !umask);
+    assert_eq!(
+        (file.mtime(), file.mtime_nsec()),
+        (1_600_000_000, 500_000_000)
+    );
+    // Directory times are restored after their contents are written.
+    let directory = fs::metadata(out.join("d")).unwrap();
+    assert_eq!(directory.mode() & 0o7777, 0o777 & !umask);
+    assert_eq!(directory.mtime(), 1_500_000_000);
+}
+
+#[test]
+fn explicit_umask_replaces_process_umask() {
+    let dir = TempDir::new().unwrap();
+    let out = dir.path().join("out");
+    let mut file = ParsedHeader::new("f", EntryType::Regular, 0);
+    file.mode = 0o777;
+    let data = archive_of(&[(file, b"")]);
+
+    let mut extractor = ArchiveExtractor::default();
+    extractor.set_umask(0o027);
+    extractor
+        .extract_from(&mut Archive::new(Cursor::new(data)), &out)
+        .unwrap();
+    let mode = fs::metadata(out.join("f")).unwrap().mode() & 0o7777;
+    assert_eq!(mode, 0o750);
+}
+
+#[test]
+fn maps_owners_by_name_unless_numeric() {
+    let dir = TempDir::new().unwrap();
+    // Changing owners needs root; elsewhere there is nothing to check.
+    if fs::metadata(dir.path()).unwrap().uid() != 0 {
+        return;
+    }
+    let member = |name: &str, uname: &str| {
+        let mut header = ParsedHeader::new(name, EntryType::Regular, 0);
+        (header.uid, header.gid) = (4242, 4343);
+        header.uname = uname.to_string();
+        header.gname = uname.to_string();
+        header
+    };
+    let data = archive_of(&[
+        (member("known", "root"), b""),
+        (member("unknown", "no-such-owner"), b""),
+    ]);
+    let owner = |path: &Path| {
+        let metadata = fs::metadata(path).unwrap();
+        (metadata.uid(), metadata.gid())
+    };
+
+    let by_name = dir.path().join("by-name");
+    let mut extractor = ArchiveExtractor::default();
+    extractor.set_same_owner(true);
+    extractor
+        .extract_from(&mut Archive::new(Cursor::new(&data)), &by_name)
+        .unwrap();
+    assert_eq!(owner(&by_name.join("known")), (0, 0));
+    assert_eq!(owner(&by_name.join("unknown")), (4242, 4343));
+
+    let numeric = dir.path().join("numeric");
+    extractor.set_numeric_owner(true);
+    extractor
+        .extract_from(&mut Archive::new(Cursor::new(&data)), &numeric)
+        .unwrap();
+    assert_eq!(owner(&numeric.join("known")), (4242, 4343));
+}
+
+#[test]
+fn extracts_fifo_and_archives_it_again() {
+    let dir = TempDir::new().unwrap();
+    let out = dir.path().join("out");
+    let mut fifo = ParsedHeader::new("pipe", EntryType::Fifo, 0);
+    fifo.mode = 0o640;
+    unpack(archive_of(&[(fifo, b"")]), &out).unwrap();
+
+    let path = out.join("pipe");
+    let metadata = fs::symlink_metadata(&path).unwrap();
+    assert!(metadata.file_type().is_fifo());
+    assert_eq!(metadata.mode() & 0o7777, 0o640 & !process_umask());
+
+    let mut builder = Builder::new(Vec::new());
+    builder.append_path(&path).unwrap();
+    let parsed = Archive::new(Cursor::new(builder.into_inner().unwrap()))
+        .next_header()
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        (parsed.name.as_str(), parsed.entry_type),
+        ("pipe", EntryType::Fifo)
+    );
+}
+
+#[test]
+fn extracts_character_device_or_notes_why_not() {
+    let dir = TempDir::new().unwrap();
+    let out = dir.path().join("out");
+    let mut null = ParsedHeader::new("null", EntryType::CharDevice, 0);
+    null.mode = 0o666;
+    (null.devmajor, null.devminor) = (1, 3);
+
+    let notes = unpack(archive_of(&[(null, b"")]), &out).unwrap();
+    match fs::symlink_metadata(out.join("null")) {
+        Ok(metadata) => {
+            assert!(metadata.file_type().is_char_device());
+            assert_eq!(metadata.rdev(), fs::metadata("/dev/null").unwrap().rdev());
+        }
+        // Unprivileged, or in a container without CAP_MKNOD.
+        Err(_) => assert_eq!(notes, ["null: skipped character device: permission denied"]),
+    }
+}
@@ -0,0 +1,113 @@
+use rustar::glob::{self, GlobError, IgnoreRules, Pattern};
+use std::{fs, path::PathBuf};
+use tempfile::TempDir;
+
+fn pattern(source: &str) -> Pattern {
+    Pattern::new(source).unwrap()
+}
+
+#[test]
+fn star_and_question_mark_stay_within_a_component() {
+    assert!(pattern("*.txt").matches("a.txt"));
+    assert!(!pattern("*.txt").matches("dir/a.txt"));
+    assert!(pattern("?.txt").matches("a.txt"));
+    assert!(!pattern("?.txt").matches("ab.txt"));
+    assert!(!pattern("a?b").matches("a/b"));
+}
+
+#[test]
+fn globstar_matches_any_number_of_components() {
+    let p = pattern("src/**/*.rs");
+    assert!(p.matches("src/lib.rs"));
+    assert!(p.matches("src/a/b/c.rs"));
+    assert!(!p.matches("lib.rs"));
+    assert!(pattern("**").matches("a/b/c"));
+    assert!(pattern("src/**").matches("src/a/b"));
+    // Only a whole component is a globstar.
+    assert!(!pattern("a**/b").matches("a/x/b"));
+}
+
+#[test]
+fn classes_match_sets_and_ranges() {
+    let p = pattern("f[a-c0].txt");
+    assert!(p.matches("fb.txt"));
+    assert!(p.matches("f0.txt"));
+    assert!(!p.matches("fd.txt"));
+    assert!(pattern("f[!a-c].txt").matches("fd.txt"));
+    assert!(!pattern("f[^a-c].txt").matches("fa.txt"));
+    assert!(pattern("[]]").matches("]"));
+    assert!(!pattern("a[/]b").matches("a/b"));
+    assert!(matches!(
+        Pattern::new("a[b"),
+        Err(GlobError::UnclosedClass(_))
+    ));
+}
+
+#[test]
+fn escapes_make_characters_literal() {
+    let p = pattern(r"report\[1\].txt");
+    assert!(p.matches("report[1].txt"));
+    assert!(!p.matches("report1.txt"));
+    assert!(!p.has_wildcards());
+    assert_eq!(p.literal().as_deref(), Some("report[1].txt"));
+    assert_eq!(pattern("*.txt").literal(), None);
+    assert!(pattern(r"\*").matches("*"));
+    assert!(matches!(
+        Pattern::new(r"a\"),
+        Err(GlobError::TrailingEscape(_))
+    ));
+}
+
+#[test]
+fn matches_prefix_selects_everything_below_a_directory() {
+    let p = pattern("./dir/");
+    assert!(p.matches_prefix("dir"));
+    assert!(p.matches_prefix("dir/a/b"));
+    assert!(!p.matches_prefix("directory/a"));
+    assert!(pattern("d*").matches_prefix("dir/a"));
+}
+
+#[test]
+fn matches_anywhere_checks_every_run_of_components() {
+    let p = pattern("*.o");
+    assert!(p.matches_anywhere("a/b/c.o"));
+    assert!(p.matches_anywhere("c.o/x"));
+    assert!(pattern("build").matches_anywhere("src/build/out"));
+    assert!(!pattern("build").matches_anywhere("src/builder"));
+}
+
+#[test]
+fn ignore_rules_follow_gitignore() {
+    let rules =
+        IgnoreRules::parse("# comment\n\n*.log\n!keep.log\ntarget/\n/doc/*.html\n[unclosed\n");
+    assert_eq!(rules.matched("a.log", false), Some(true));
+    assert_eq!(rules.matched("sub/b.log", false), Some(true));
+    assert_eq!(rules.matched("sub/keep.log", false), Some(false));
+    assert_eq!(rules.matched("target", true), Some(true));
+    assert_eq!(rules.matched("target", false), None);
+    assert_eq!(rules.matched("doc/a.html", false), Some(true));
+    assert_eq!(rules.matched("sub/doc/a.html", false), None);
+    assert_eq!(rules.matched("main.rs", false), None);
+}
+
+#[test]
+fn expand_returns_literals_unescaped_and_matches_sorted() {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path().to_str().unwrap();
+    for name in ["b.txt", "a.txt", ".hidden.txt", "c.md"] {
+        fs::write(dir.path().join(name), "").unwrap();
+    }
+
+    assert_eq!(
+        glob::expand(&format!("{root}/*.txt")).unwrap(),
+        [dir.path().join("a.txt"), dir.path().join("b.txt")]
+    );
+    assert_eq!(
+        glob::expand(r"missing\[1\].txt").unwrap(),
+        [PathBuf::from("missing[1].txt")]
+    );
+    assert!(matches!(
+        glob::expand(&format!("{root}/*.rs")),
+        Err(GlobError::NoMatch(_))
+    ));
+}
@@ -0,0 +1,197 @@
+use rustar::{
+    Archive, ArchiverError, Builder, EntryType, HeaderFormat, ParsedHeader,
+    header::{HeaderBuilder, HeaderError, constants::*},
+};
+use std::{
+    fs::{self, File},
+    io::Cursor,
+    os::unix::fs::MetadataExt,
+    time::{Duration, SystemTime},
+};
+use tempfile::TempDir;
+
+const FORMATS: [HeaderFormat; 2] = [HeaderFormat::Pax, HeaderFormat::Gnu];
+
+/// Serializes a header and reads it back, applying any pax or GNU
+/// extension headers. Member data is not needed to parse the header.
+fn round_trip(header: &ParsedHeader, format: HeaderFormat) -> ParsedHeader {
+    let blocks = HeaderBuilder::build(header, format).unwrap();
+    Archive::new(Cursor::new(blocks))
+        .next_header()
+        .unwrap()
+        .unwrap()
+}
+
+/// The last header block, which holds the fixed-width fields.
+fn member_block(header: &ParsedHeader, format: HeaderFormat) -> Vec<u8> {
+    let blocks = HeaderBuilder::build(header, format).unwrap();
+    blocks[blocks.len() - BLOCK_SIZE..].to_vec()
+}
+
+#[test]
+fn size_round_trips_at_octal_boundary() {
+    for format in FORMATS {
+        for size in [8u64.pow(11) - 1, 8u64.pow(11), u64::MAX - 511] {
+            let header = ParsedHeader::new("f", EntryType::Regular, size);
+            assert_eq!(round_trip(&header, format).size, size, "{format:?}");
+        }
+    }
+}
+
+#[test]
+fn size_that_cannot_be_padded_is_an_error() {
+    let header = ParsedHeader::new("f", EntryType::Regular, u64::MAX - 510);
+    for format in FORMATS {
+        let blocks = HeaderBuilder::build(&header, format).unwrap();
+        let mut archive = Archive::new(Cursor::new(blocks));
+        let mut entries = archive.entries();
+        assert!(matches!(
+            entries.next(),
+            Some(Err(ArchiverError::HeaderError(
+                HeaderError::NumericOverflow
+            )))
+        ));
+        assert!(entries.next().is_none());
+    }
+}
+
+#[test]
+fn size_uses_octal_until_it_no_longer_fits() {
+    let fits = ParsedHeader::new("f", EntryType::Regular, 8u64.pow(11) - 1);
+    let overflows = ParsedHeader::new("f", EntryType::Regular, 8u64.pow(11));
+    for format in FORMATS {
+        assert_eq!(
+            &member_block(&fits, format)[SIZE_FIELD][..11],
+            b"77777777777"
+        );
+    }
+    // Pax stores the size in a record; GNU switches to base-256.
+    assert_eq!(
+        HeaderBuilder::build(&overflows, HeaderFormat::Pax)
+            .unwrap()
+            .len(),
+        3 * BLOCK_SIZE
+    );
+    let gnu = member_block(&overflows, HeaderFormat::Gnu);
+    assert_eq!(gnu[SIZE_FIELD.start], 0x80);
+    assert_eq!(&gnu[SIZE_FIELD][4..], &8u64.pow(11).to_be_bytes());
+}
+
+#[test]
+fn uid_round_trips_at_octal_boundary() {
+    for format in FORMATS {
+        for id in [2097151, 2097152] {
+            let mut header = ParsedHeader::new("f", EntryType::Regular, 0);
+            header.uid = id;
+            header.gid = id;
+            let parsed = round_trip(&header, format);
+            assert_eq!((parsed.uid, parsed.gid), (id, id), "{format:?}");
+        }
+    }
+}
+
+#[test]
+fn uid_at_u64_limit() {
+    let mut header = ParsedHeader::new("f", EntryType::Regular, 0);
+    header.uid = u64::MAX;
+    assert_eq!(round_trip(&header, HeaderFormat::Pax).uid, u64::MAX);
+    // Seven base-256 bytes hold at most 56 bits.
+    assert!(matches!(
+        HeaderBuilder::build(&header, HeaderFormat::Gnu),
+        Err(HeaderError::NumericOverflow)
+    ));
+    header.uid = (1 << 56) - 1;
+    assert_eq!(round_trip(&header, HeaderFormat::Gnu).uid, (1 << 56) - 1);
+}
+
+#[test]
+fn sub_second_times_round_trip_through_pax() {
+    let mut header = ParsedHeader::new("f", EntryType::Regular, 0);
+    header.mtime = 1_700_000_000;
+    header.mtime_nsec = 123_456_789;
+    header.atime = Some(Duration::new(1_700_000_001, 5));
+    header.ctime = Some(Duration::new(1_700_000_002, 0));
+    let parsed = round_trip(&header, HeaderFormat::Pax);
+    assert_eq!(
+        (parsed.mtime, parsed.mtime_nsec),
+        (1_700_000_000, 123_456_789)
+    );
+    assert_eq!(parsed.atime, header.atime);
+    // Whole-second change times are not worth an extended header.
+    assert_eq!(parsed.ctime, None);
+
+    header.mtime_nsec = 0;
+    header.atime = Some(Duration::from_secs(1_700_000_001));
+    assert_eq!(
+        HeaderBuilder::build(&header, HeaderFormat::Pax)
+            .unwrap()
+            .len(),
+        BLOCK_SIZE
+    );
+}
+
+#[test]
+fn create_stores_sub_second_mtime() {
+    let dir = TempDir::new().unwrap();
+    let file = dir.path().join("f");
+    fs::write(&file, "f").unwrap();
+    let mtime = SystemTime::UNIX_EPOCH + Duration::new(1_700_000_000, 250_000_000);
+    File::options()
+        .write(true)
+        .open(&file)
+        .unwrap()
+        .set_modified(mtime)
+        .unwrap();
+
+    let mut builder = Builder::new(Vec::new());
+    builder.append_path(&file).unwrap();
+    let data = builder.into_inner().unwrap();
+    let parsed = Archive::new(Cursor::new(data))
+        .next_header()
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        (parsed.mtime, parsed.mtime_nsec),
+        (1_700_000_000, 250_000_000)
+    );
+    let metadata = fs::metadata(&file).unwrap();
+    let ctime = Duration::new(metadata.ctime() as u64, metadata.ctime_nsec() as u32);
+    if ctime.subsec_nanos() != 0 {
+        assert_eq!(parsed.ctime, Some(ctime));
+    }
+}
+
+#[test]
+fn owner_names_round_trip() {
+    for format in FORMATS {
+        let mut header = ParsedHeader::new("f", EntryType::Regular, 0);
+        header.uname = "alice".to_string();
+        header.gname = "staff".to_string();
+        let parsed = round_trip(&header, format);
+        assert_eq!(
+            (parsed.uname.as_str(), parsed.gname.as_str()),
+            ("alice", "staff")
+        );
+    }
+    // Names longer than the 32-byte fields go to pax records.
+    let mut header = ParsedHeader::new("f", EntryType::Regular, 0);
+    header.uname = "u".repeat(40);
+    header.gname = "g".repeat(40);
+    let parsed = round_trip(&header, HeaderFormat::Pax);
+    assert_eq!((parsed.uname, parsed.gname), (header.uname, header.gname));
+}
+
+#[test]
+fn device_numbers_round_trip() {
+    for format in FORMATS {
+        for entry_type in [EntryType::CharDevice, EntryType::BlockDevice] {
+            for (major, minor) in [(1, 3), (259, 2097151)] {
+                let mut header = ParsedHeader::new("dev", entry_type, 0);
+                (header.devmajor, header.devminor) = (major, minor);
+                let parsed = round_trip(&header, format);
+                assert_eq!(parsed.entry_type, entry_type);
+                assert_eq!((parsed.devmajor, parsed.devminor), (major, minor));
+            }
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use rustar::Archiver;
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tempfile::TempDir;
+
+fn names(archiver: &Archiver, archive: &Path) -> Vec<String> {
+    archiver
+        .list(archive)
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.name)
+        .collect()
+}
+
+/// Moves the file's mtime `secs` seconds away from the current one.
+fn touch(path: &Path, secs: i64) {
+    let mtime = fs::metadata(path).unwrap().modified().unwrap();
+    let mtime = if secs >= 0 {
+        mtime + Duration::from_secs(secs as u64)
+    } else {
+        mtime - Duration::from_secs(-secs as u64)
+    };
+    File::options()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_modified(mtime)
+        .unwrap();
+}
+
+fn setup(dir: &TempDir, archive_name: &str) -> (PathBuf, [PathBuf; 2]) {
+    let (a, b) = (dir.path().join("a"), dir.path().join("b"));
+    fs::write(&a, "a").unwrap();
+    fs::write(&b, "b").unwrap();
+    // Well in the past, so that a later touch is clearly newer.
+    touch(&a, -100);
+    touch(&b, -100);
+    let archive = dir.path().join(archive_name);
+    Archiver::default().create(&archive, vec![&a, &b]).unwrap();
+    (archive, [a, b])
+}
+
+#[test]
+fn skips_unchanged_files() {
+    let dir = TempDir::new().unwrap();
+    let (archive, [a, b]) = setup(&dir, "out.tar");
+    let before = fs::read(&archive).unwrap();
+
+    let notes = Archiver::default().update(&archive, vec![&a, &b]).unwrap();
+    assert_eq!(
+        notes,
+        [
+            "a: not newer than archived copy",
+            "b: not newer than archived copy"
+        ]
+    );
+    assert_eq!(fs::read(&archive).unwrap(), before);
+}
+
+#[test]
+fn appends_newer_and_new_files() {
+    let dir = TempDir::new().unwrap();
+    let (archive, [a, b]) = setup(&dir, "out.tar");
+    touch(&b, 50);
+    let c = dir.path().join("c");
+    fs::write(&c, "c").unwrap();
+
+    let archiver = Archiver::default();
+    let notes = archiver.update(&archive, vec![&a, &b, &c]).unwrap();
+    assert_eq!(notes, ["a: not newer than archived copy"]);
+    assert_eq!(names(&archiver, &archive), ["a", "b", "b", "c"]);
+
+    // The new copies are now the ones to compare against.
+    let notes = archiver.update(&archive, vec![&b, &c]).unwrap();
+    assert_eq!(notes.len(), 2);
+    assert_eq!(names(&archiver, &archive).len(), 4);
+}
+
+#[test]
+fn rewrites_compressed_archive_with_its_codec() {
+    let dir = TempDir::new().unwrap();
+    let (archive, [a, b]) = setup(&dir, "out.tar.gz");
+    touch(&a, 50);
+
+    let archiver = Archiver::default();
+    archiver.update(&archive, vec![&a, &b]).unwrap();
+    assert_eq!(&fs::read(&archive).unwrap()[..2], &[0x1f, 0x8b]);
+    assert_eq!(names(&archiver, &archive), ["a", "b", "a"]);
+}
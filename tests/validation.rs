@@ -0,0 +1,47 @@
+use rustar::{
+    Archive, ArchiverError, EntryType, HeaderFormat, ParsedHeader,
+    header::{HeaderBuilder, constants::*},
+    validation::{ArchiveStructureValidator, ValidationError},
+};
+use std::{fs, io::Cursor};
+use tempfile::TempDir;
+
+/// A single GNU header block followed by the end-of-archive marker, without
+/// any member data.
+fn header_only(header: &ParsedHeader) -> Vec<u8> {
+    let mut data = HeaderBuilder::build(header, HeaderFormat::Gnu).unwrap();
+    data.extend([0u8; BLOCK_SIZE * END_MARKER_BLOCKS]);
+    data
+}
+
+fn validate(data: &[u8]) -> Result<(), ValidationError> {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("a.tar");
+    fs::write(&path, data).unwrap();
+    ArchiveStructureValidator::validate(&path)
+}
+
+#[test]
+fn rejects_size_that_cannot_be_padded() {
+    let data = header_only(&ParsedHeader::new("f", EntryType::Regular, u64::MAX));
+    assert!(matches!(
+        validate(&data),
+        Err(ValidationError::InvalidStructure(_))
+    ));
+}
+
+#[test]
+fn rejects_oversized_long_name() {
+    let header = ParsedHeader::new(GNU_LONGLINK_NAME, EntryType::GnuLongName, 1 << 60);
+    let data = header_only(&header);
+    assert!(matches!(
+        validate(&data),
+        Err(ValidationError::InvalidStructure(_))
+    ));
+    assert!(matches!(
+        Archive::new(Cursor::new(data)).next_header(),
+        Err(ArchiverError::Validation(
+            ValidationError::InvalidStructure(_)
+        ))
+    ));
+}